@@ -0,0 +1,91 @@
+// Material of the chunk meshes: the atlas tile of each face, repeated once per block, lit like the
+// standard material.
+
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::mesh_functions
+#import bevy_pbr::pbr_types
+#import bevy_pbr::utils
+#import bevy_pbr::clustered_forward
+#import bevy_pbr::lighting
+#import bevy_pbr::shadows
+#import bevy_pbr::pbr_functions
+
+struct Surface {
+    color: vec4<f32>,
+    emissive: vec4<f32>,
+    roughness: f32,
+};
+
+struct BlockMaterial {
+    // Surface of each block type, indexed by `BlockType`.
+    surfaces: array<Surface, 16>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: BlockMaterial;
+@group(1) @binding(1)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var atlas_sampler: sampler;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) block_type: u32,
+    @location(4) atlas_rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) @interpolate(flat) block_type: u32,
+    @location(4) @interpolate(flat) atlas_rect: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.clip_position = mesh_position_world_to_clip(out.world_position);
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    out.uv = vertex.uv;
+    out.block_type = vertex.block_type;
+    out.atlas_rect = vertex.atlas_rect;
+    return out;
+}
+
+struct FragmentInput {
+    @builtin(position) frag_coord: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) @interpolate(flat) block_type: u32,
+    @location(4) @interpolate(flat) atlas_rect: vec4<f32>,
+};
+
+@fragment
+fn fragment(in: FragmentInput) -> @location(0) vec4<f32> {
+    let surface = material.surfaces[min(in.block_type, 15u)];
+    // `uv` counts blocks, so its fractional part repeats the tile over merged faces.
+    let uv = mix(in.atlas_rect.xy, in.atlas_rect.zw, fract(in.uv));
+
+    var pbr_input: PbrInput = pbr_input_new();
+    pbr_input.material.base_color = surface.color * textureSample(atlas_texture, atlas_sampler, uv);
+    pbr_input.material.emissive = surface.emissive;
+    pbr_input.material.perceptual_roughness = surface.roughness;
+    if (pbr_input.material.base_color.a < 1.0) {
+        pbr_input.material.flags = STANDARD_MATERIAL_FLAGS_ALPHA_MODE_BLEND;
+    }
+    pbr_input.frag_coord = in.frag_coord;
+    pbr_input.world_position = in.world_position;
+    pbr_input.world_normal = in.world_normal;
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.N = normalize(in.world_normal);
+    pbr_input.V = calculate_view(in.world_position, pbr_input.is_orthographic);
+
+    return tone_mapping(pbr(pbr_input));
+}
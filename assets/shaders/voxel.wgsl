@@ -0,0 +1,68 @@
+// Material of the chunk meshes with `--voxel-shader`. Saved changes are reloaded while the game
+// runs.
+
+#import bevy_pbr::mesh_view_bindings
+#import bevy_pbr::mesh_bindings
+#import bevy_pbr::mesh_functions
+
+struct VoxelMaterial {
+    // Color of each block type, indexed by `BlockType`.
+    palette: array<vec4<f32>, 16>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: VoxelMaterial;
+@group(1) @binding(1)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var atlas_sampler: sampler;
+
+// Direction the light comes from, and how much of it reaches faces turned away from it.
+let SUN_DIRECTION: vec3<f32> = vec3<f32>(0.4, 1.0, 0.3);
+let AMBIENT: f32 = 0.45;
+// Brightness of a corner closed on all sides.
+let MIN_OCCLUSION: f32 = 0.35;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) block_type: u32,
+    @location(3) occlusion: f32,
+    @location(4) uv: vec2<f32>,
+    @location(5) atlas_rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) @interpolate(flat) atlas_rect: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let normal = mesh_normal_local_to_world(vertex.normal);
+    let diffuse = max(dot(normal, normalize(SUN_DIRECTION)), 0.0);
+    let light = (AMBIENT + (1.0 - AMBIENT) * diffuse) * mix(MIN_OCCLUSION, 1.0, vertex.occlusion);
+    let color = material.palette[min(vertex.block_type, 15u)];
+
+    var out: VertexOutput;
+    out.clip_position = mesh_position_local_to_clip(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.color = vec4<f32>(color.rgb * light, color.a);
+    out.uv = vertex.uv;
+    out.atlas_rect = vertex.atlas_rect;
+    return out;
+}
+
+struct FragmentInput {
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) @interpolate(flat) atlas_rect: vec4<f32>,
+};
+
+@fragment
+fn fragment(input: FragmentInput) -> @location(0) vec4<f32> {
+    // `uv` counts blocks, so its fractional part repeats the tile over merged faces.
+    let uv = mix(input.atlas_rect.xy, input.atlas_rect.zw, fract(input.uv));
+    return input.color * textureSample(atlas_texture, atlas_sampler, uv);
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::history::{BlockPlaced, BlockRemoved};
+use crate::input_map::{Action, InputMap};
+
+const ANALYTICS_PATH: &str = "analytics.ron";
+/// Seconds between two writes of the current session, so a crash loses little of it.
+const SAVE_INTERVAL: f32 = 60.0;
+/// Width of the frame time buckets, in milliseconds.
+const BUCKET_MS: f32 = 0.5;
+/// Frame times above `BUCKET_MS * FRAME_BUCKETS` all go in the last bucket.
+const FRAME_BUCKETS: usize = 400;
+/// Actions listed in the dashboard.
+const TOP_ACTIONS: usize = 5;
+
+/// Frame times of a session, in milliseconds.
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+struct FramePercentiles {
+    p50: f32,
+    p95: f32,
+    p99: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    /// Seconds since the UNIX epoch at which the session started.
+    started_at: u64,
+    seconds: f32,
+    /// Times each action was triggered, by description. Actions sharing a button, like placing a
+    /// block and picking a box corner, are all counted.
+    actions: BTreeMap<String, u32>,
+    blocks_placed: usize,
+    blocks_removed: usize,
+    frame_ms: FramePercentiles,
+}
+
+fn load_sessions() -> Vec<SessionRecord> {
+    fs::read_to_string(ANALYTICS_PATH)
+        .ok()
+        .and_then(|content| ron::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The session being recorded, and the ones recorded before it.
+struct Analytics {
+    previous: Vec<SessionRecord>,
+    current: SessionRecord,
+    /// Frames per duration, `BUCKET_MS` wide each, for percentiles without keeping every frame.
+    frame_buckets: Vec<u32>,
+    save_timer: Timer,
+}
+
+impl Analytics {
+    fn new() -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        Analytics {
+            previous: load_sessions(),
+            current: SessionRecord {
+                started_at,
+                seconds: 0.0,
+                actions: BTreeMap::new(),
+                blocks_placed: 0,
+                blocks_removed: 0,
+                frame_ms: FramePercentiles::default(),
+            },
+            frame_buckets: vec![0; FRAME_BUCKETS],
+            save_timer: Timer::from_seconds(SAVE_INTERVAL, true),
+        }
+    }
+
+    /// Upper bound of the bucket holding the `fraction` of frames, in milliseconds.
+    fn percentile(&self, fraction: f32) -> f32 {
+        let total: u32 = self.frame_buckets.iter().sum();
+        let target = (total as f32 * fraction).ceil() as u32;
+
+        let mut count = 0;
+        for (bucket, frames) in self.frame_buckets.iter().enumerate() {
+            count += frames;
+            if count >= target.max(1) {
+                return (bucket + 1) as f32 * BUCKET_MS;
+            }
+        }
+        0.0
+    }
+
+    fn update_percentiles(&mut self) {
+        self.current.frame_ms = FramePercentiles {
+            p50: self.percentile(0.5),
+            p95: self.percentile(0.95),
+            p99: self.percentile(0.99),
+        };
+    }
+
+    /// Writes the previous sessions followed by the current one.
+    fn save(&self) {
+        let sessions: Vec<&SessionRecord> = self.previous.iter().chain([&self.current]).collect();
+
+        let result = ron::ser::to_string_pretty(&sessions, PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|content| fs::write(ANALYTICS_PATH, content).map_err(|err| err.to_string()));
+
+        if let Err(err) = result {
+            error!("Could not save usage analytics: {}", err);
+        }
+    }
+
+    /// Times each action was triggered, over every session.
+    fn total_actions(&self) -> BTreeMap<&str, u32> {
+        let mut totals = BTreeMap::new();
+        for session in self.previous.iter().chain([&self.current]) {
+            for (action, count) in &session.actions {
+                *totals.entry(action.as_str()).or_default() += count;
+            }
+        }
+        totals
+    }
+}
+
+#[derive(Component)]
+struct AnalyticsDashboard;
+
+fn record_usage(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut placed: EventReader<BlockPlaced>,
+    mut removed: EventReader<BlockRemoved>,
+    mut analytics: ResMut<Analytics>,
+) {
+    analytics.current.seconds += time.delta_seconds();
+
+    let bucket = (time.delta_seconds() * 1000.0 / BUCKET_MS) as usize;
+    analytics.frame_buckets[bucket.min(FRAME_BUCKETS - 1)] += 1;
+
+    for (action, _) in input_map.bindings() {
+        if input_map.just_pressed(action, &keys, &mouse) {
+            *analytics
+                .current
+                .actions
+                .entry(action.description().to_string())
+                .or_default() += 1;
+        }
+    }
+
+    analytics.current.blocks_placed += placed.iter().count();
+    analytics.current.blocks_removed += removed.iter().count();
+}
+
+/// Writes the sessions every `SAVE_INTERVAL` and when the game closes. Runs last, to see the exit
+/// event sent during the frame.
+fn save_analytics(
+    time: Res<Time>,
+    mut exit: EventReader<AppExit>,
+    mut analytics: ResMut<Analytics>,
+) {
+    let exiting = exit.iter().next().is_some();
+    if !analytics.save_timer.tick(time.delta()).just_finished() && !exiting {
+        return;
+    }
+
+    analytics.update_percentiles();
+    analytics.save();
+}
+
+fn toggle_dashboard(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<AnalyticsDashboard>>,
+) {
+    if !input_map.just_pressed(Action::ToggleAnalytics, &keys, &mouse) {
+        return;
+    }
+
+    if !query.is_empty() {
+        for entity in &query {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(8.0),
+                    left: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(AnalyticsDashboard);
+}
+
+fn update_dashboard(
+    mut analytics: ResMut<Analytics>,
+    mut query: Query<&mut Text, With<AnalyticsDashboard>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    analytics.update_percentiles();
+    let current = &analytics.current;
+    let total_hours = analytics
+        .previous
+        .iter()
+        .chain([current])
+        .map(|session| session.seconds)
+        .sum::<f32>()
+        / 3600.0;
+
+    let mut lines = vec![
+        "Usage, stored locally".to_string(),
+        format!(
+            "This session: {:.0} min, {} blocks placed, {} removed",
+            current.seconds / 60.0,
+            current.blocks_placed,
+            current.blocks_removed
+        ),
+        format!(
+            "All sessions: {}, {:.1} h",
+            analytics.previous.len() + 1,
+            total_hours
+        ),
+        format!(
+            "Frame time: {:.1} ms median, {:.1} ms p95, {:.1} ms p99",
+            current.frame_ms.p50, current.frame_ms.p95, current.frame_ms.p99
+        ),
+        "Most used:".to_string(),
+    ];
+
+    let mut actions: Vec<_> = analytics.total_actions().into_iter().collect();
+    actions.sort_by(|(_, a), (_, b)| b.cmp(a));
+    lines.extend(
+        actions
+            .into_iter()
+            .take(TOP_ACTIONS)
+            .map(|(action, count)| format!("  {}: {}", action, count)),
+    );
+
+    for mut text in &mut query {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Opt-in usage statistics, enabled with `--analytics`: session lengths, actions used and frame
+/// times, kept in a local file and never sent anywhere. U shows them.
+pub struct AnalyticsPlugin;
+
+impl Plugin for AnalyticsPlugin {
+    fn build(&self, app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--analytics") {
+            return;
+        }
+
+        app.insert_resource(Analytics::new())
+            .add_system(record_usage)
+            .add_system(toggle_dashboard)
+            .add_system(update_dashboard.after(record_usage))
+            .add_system_to_stage(CoreStage::Last, save_analytics);
+    }
+}
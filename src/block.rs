@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::render::texture::ImageSampler;
+
+use crate::input_map::{Action, InputMap};
+use crate::{cli_arg, BlockPosition};
+
+/// Tiles per side of the texture atlas.
+const ATLAS_TILES: u32 = 4;
+const DEFAULT_ATLAS_PATH: &str = "textures/blocks.png";
+
+/// Kind of a placed block, an index in the `BlockRegistry`.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BlockType(pub u16);
+
+impl BlockType {
+    pub const DIRT: BlockType = BlockType(0);
+    pub const STONE: BlockType = BlockType(1);
+}
+
+/// Where a block type can be placed.
+#[derive(Clone, Copy, Default)]
+pub struct PlacementRules {
+    /// The cell below must hold a block or a floor tile.
+    pub needs_support: bool,
+}
+
+/// Area of the texture atlas drawn on a face, in UV coordinates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct UvRect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl UvRect {
+    /// The tile at `column` and `row` of the atlas, counted from its top left corner.
+    pub fn tile(column: u32, row: u32) -> Self {
+        let size = 1.0 / ATLAS_TILES as f32;
+        let min = Vec2::new(column as f32, row as f32) * size;
+
+        UvRect {
+            min,
+            max: min + Vec2::splat(size),
+        }
+    }
+
+    /// White tile in the last corner of the atlas, for the types drawn in a plain color.
+    pub fn plain() -> Self {
+        UvRect::tile(ATLAS_TILES - 1, ATLAS_TILES - 1)
+    }
+}
+
+/// Tiles of the texture atlas drawn on the faces of a block type.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BlockFaces {
+    pub top: UvRect,
+    pub side: UvRect,
+    pub bottom: UvRect,
+}
+
+impl BlockFaces {
+    pub fn all(rect: UvRect) -> Self {
+        BlockFaces {
+            top: rect,
+            side: rect,
+            bottom: rect,
+        }
+    }
+
+    /// The tile drawn on the face with `normal`.
+    pub fn rect(&self, normal: Vec3) -> UvRect {
+        if normal.y > 0.0 {
+            self.top
+        } else if normal.y < 0.0 {
+            self.bottom
+        } else {
+            self.side
+        }
+    }
+}
+
+pub struct BlockDefinition {
+    pub name: &'static str,
+    pub material: Handle<StandardMaterial>,
+    pub placement: PlacementRules,
+    /// Seconds of mining needed to break the block in survival-lite mode.
+    pub hardness: f32,
+    /// Color of the point light emitted by the block, if any.
+    pub light: Option<Color>,
+    /// Lets the faces of the blocks behind it show through.
+    pub transparent: bool,
+    /// Tiles of the texture atlas on its faces, drawn in their own colors. Without them the block
+    /// is drawn in the plain color of its material.
+    pub faces: Option<BlockFaces>,
+}
+
+/// Every block type, with the material shared by all blocks of that type.
+pub struct BlockRegistry {
+    blocks: Vec<BlockDefinition>,
+    atlas: Option<Handle<Image>>,
+}
+
+impl BlockRegistry {
+    /// Texture atlas of the block faces, `textures/blocks.png` unless `--atlas <path>` is given,
+    /// the path being relative to the `assets` directory.
+    ///
+    /// The atlas is a grid of `ATLAS_TILES` by `ATLAS_TILES` tiles. Its first row holds grass,
+    /// grass seen from the side, dirt and stone, and the second one bark and wood rings. Its last
+    /// tile is white, see `UvRect::plain`.
+    pub fn atlas(&self) -> Option<&Handle<Image>> {
+        self.atlas.as_ref()
+    }
+
+    pub fn get(&self, block_type: BlockType) -> Option<&BlockDefinition> {
+        self.blocks.get(block_type.0 as usize)
+    }
+
+    /// Color multiplying the atlas tile of the faces of a type: white for the textured types,
+    /// whose tiles have their own colors, and the color of the material for the others.
+    pub fn face_color(
+        &self,
+        block_type: BlockType,
+        materials: &Assets<StandardMaterial>,
+    ) -> Option<Color> {
+        let definition = self.get(block_type)?;
+        let color = materials.get(&definition.material)?.base_color;
+
+        Some(match definition.faces {
+            Some(_) => Color::rgba(1.0, 1.0, 1.0, color.a()),
+            None => color,
+        })
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = BlockType> {
+        (0..self.blocks.len() as u16).map(BlockType)
+    }
+
+    /// Checks the placement rules of the type for a block at `position`.
+    pub fn can_place(
+        &self,
+        block_type: BlockType,
+        position: BlockPosition,
+        map: &BlockMap,
+    ) -> bool {
+        let definition = match self.get(block_type) {
+            Some(definition) => definition,
+            None => return false,
+        };
+
+        let below = BlockPosition {
+            y: position.y - 1,
+            ..position
+        };
+
+        position.in_bounds()
+            && !map.contains(position)
+            && (!definition.placement.needs_support || map.contains(below))
+    }
+}
+
+/// Meshes and materials shared by the cells drawn on their own rather than by their chunk, like
+/// floor tiles and map tables, instead of one asset per entity.
+pub struct BlockAssets {
+    pub cube: Handle<Mesh>,
+    pub floor_tile: Handle<Mesh>,
+    pub floor_material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for BlockAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+        let floor_tile = meshes.add(Mesh::from(shape::Plane { size: 1.0 }));
+
+        let floor_material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::rgb(0.1, 0.8, 0.1).into());
+
+        BlockAssets {
+            cube,
+            floor_tile,
+            floor_material,
+        }
+    }
+}
+
+impl FromWorld for BlockRegistry {
+    fn from_world(world: &mut World) -> Self {
+        // Headless apps, like the optimizer, have no images to load it into.
+        let atlas = world.contains_resource::<Assets<Image>>().then(|| {
+            let path = cli_arg("--atlas").unwrap_or_else(|| DEFAULT_ATLAS_PATH.to_string());
+            world.resource::<AssetServer>().load(path.as_str())
+        });
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+
+        let blocks = vec![
+            BlockDefinition {
+                name: "Dirt",
+                material: materials.add(Color::rgb(0.45, 0.3, 0.15).into()),
+                // Dirt doesn't float.
+                placement: PlacementRules {
+                    needs_support: true,
+                },
+                hardness: 0.5,
+                light: None,
+                transparent: false,
+                // Grass grows on top.
+                faces: Some(BlockFaces {
+                    top: UvRect::tile(0, 0),
+                    side: UvRect::tile(1, 0),
+                    bottom: UvRect::tile(2, 0),
+                }),
+            },
+            BlockDefinition {
+                name: "Stone",
+                material: materials.add(Color::rgb(0.8, 0.8, 0.8).into()),
+                placement: PlacementRules::default(),
+                hardness: 1.5,
+                light: None,
+                transparent: false,
+                faces: Some(BlockFaces::all(UvRect::tile(3, 0))),
+            },
+            BlockDefinition {
+                name: "Glass",
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.7, 0.9, 1.0, 0.35),
+                    alpha_mode: AlphaMode::Blend,
+                    perceptual_roughness: 0.1,
+                    ..default()
+                }),
+                placement: PlacementRules::default(),
+                hardness: 0.3,
+                light: None,
+                transparent: true,
+                faces: None,
+            },
+            BlockDefinition {
+                name: "Light",
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(1.0, 0.9, 0.6),
+                    emissive: Color::rgb(1.0, 0.9, 0.6),
+                    ..default()
+                }),
+                placement: PlacementRules::default(),
+                hardness: 0.3,
+                light: Some(Color::rgb(1.0, 0.9, 0.6)),
+                transparent: false,
+                faces: None,
+            },
+            BlockDefinition {
+                name: "Wood",
+                material: materials.add(Color::rgb(0.55, 0.4, 0.25).into()),
+                placement: PlacementRules::default(),
+                hardness: 1.0,
+                light: None,
+                transparent: false,
+                faces: Some(BlockFaces {
+                    top: UvRect::tile(1, 1),
+                    side: UvRect::tile(0, 1),
+                    bottom: UvRect::tile(1, 1),
+                }),
+            },
+        ];
+
+        BlockRegistry { blocks, atlas }
+    }
+}
+
+/// Entity in each occupied cell, blocks and floor tiles alike, for constant time lookups.
+#[derive(Default)]
+pub struct BlockMap {
+    cells: HashMap<BlockPosition, Entity>,
+    positions: HashMap<Entity, BlockPosition>,
+}
+
+impl BlockMap {
+    pub fn get(&self, position: BlockPosition) -> Option<Entity> {
+        self.cells.get(&position).copied()
+    }
+
+    pub fn contains(&self, position: BlockPosition) -> bool {
+        self.cells.contains_key(&position)
+    }
+
+    /// The occupied cells among the six sharing a face with `position`.
+    pub fn neighbours(
+        &self,
+        position: BlockPosition,
+    ) -> impl Iterator<Item = (BlockPosition, Entity)> + '_ {
+        const STEPS: [(i64, i64, i64); 6] = [
+            (1, 0, 0),
+            (-1, 0, 0),
+            (0, 1, 0),
+            (0, -1, 0),
+            (0, 0, 1),
+            (0, 0, -1),
+        ];
+
+        STEPS.iter().filter_map(move |(dx, dy, dz)| {
+            let neighbour = BlockPosition {
+                x: position.x + dx,
+                y: position.y + dy,
+                z: position.z + dz,
+            };
+            Some((neighbour, self.get(neighbour)?))
+        })
+    }
+
+    pub fn insert(&mut self, position: BlockPosition, entity: Entity) {
+        if let Some(previous) = self.cells.insert(position, entity) {
+            self.positions.remove(&previous);
+        }
+        self.positions.insert(entity, position);
+    }
+
+    pub fn remove(&mut self, position: BlockPosition) -> Option<Entity> {
+        let entity = self.cells.remove(&position)?;
+        self.positions.remove(&entity);
+        Some(entity)
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        if let Some(position) = self.positions.remove(&entity) {
+            self.cells.remove(&position);
+        }
+    }
+}
+
+/// Catches the blocks spawned or despawned outside of `EditBlocks`, like loaded or generated ones.
+///
+/// Runs last, once every stage had its commands applied.
+fn sync_block_map(
+    mut map: ResMut<BlockMap>,
+    added: Query<(Entity, &BlockPosition), Added<BlockPosition>>,
+    removed: RemovedComponents<BlockPosition>,
+) {
+    for entity in removed.iter() {
+        map.remove_entity(entity);
+    }
+
+    for (entity, position) in &added {
+        if map.get(*position) != Some(entity) {
+            map.insert(*position, entity);
+        }
+    }
+}
+
+/// Tags the point light emitted by a light block, as opposed to the scene lights.
+#[derive(Component)]
+pub struct BlockLight;
+
+/// Block type placed by the placement tools.
+pub struct SelectedBlock(pub BlockType);
+
+impl Default for SelectedBlock {
+    fn default() -> Self {
+        SelectedBlock(BlockType::STONE)
+    }
+}
+
+fn cycle_block_type(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    mut selected: ResMut<SelectedBlock>,
+) {
+    if !input_map.just_pressed(Action::CycleBlockType, &keys, &mouse) {
+        return;
+    }
+
+    let count = registry.types().count() as u16;
+    selected.0 = BlockType((selected.0 .0 + 1) % count);
+
+    if let Some(definition) = registry.get(selected.0) {
+        info!("Placing {} blocks", definition.name);
+    }
+}
+
+/// Samples the atlas without filtering once loaded, so its tiles stay sharp and don't bleed into
+/// each other.
+fn sample_atlas_nearest(
+    registry: Res<BlockRegistry>,
+    mut events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let atlas = match registry.atlas() {
+        Some(atlas) => atlas,
+        None => return,
+    };
+
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } = event {
+            if handle == atlas {
+                if let Some(image) = images.get_mut(handle) {
+                    image.sampler_descriptor = ImageSampler::nearest();
+                }
+            }
+        }
+    }
+}
+
+/// Block types and the one currently selected for placement.
+pub struct BlockPlugin;
+
+impl Plugin for BlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockAssets>()
+            .init_resource::<BlockRegistry>()
+            .init_resource::<SelectedBlock>()
+            .init_resource::<BlockMap>()
+            .add_system(cycle_block_type)
+            .add_system(sample_atlas_nearest)
+            .add_system_to_stage(CoreStage::Last, sync_block_map);
+    }
+}
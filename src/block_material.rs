@@ -0,0 +1,128 @@
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderRef, ShaderType, SpecializedMeshPipelineError,
+};
+
+use crate::block::BlockRegistry;
+use crate::mesher::{ATTRIBUTE_ATLAS_RECT, ATTRIBUTE_BLOCK_TYPE};
+use crate::voxel_material::{self, PALETTE_SIZE};
+
+/// Standard material properties of a block type.
+#[derive(ShaderType, Clone, Copy, Default)]
+struct Surface {
+    color: Vec4,
+    emissive: Vec4,
+    roughness: f32,
+}
+
+#[derive(ShaderType, Clone)]
+struct Surfaces {
+    surfaces: [Surface; PALETTE_SIZE],
+}
+
+/// Material of the chunk meshes, drawing the atlas tile of each face with the surface of its block
+/// type in `assets/shaders/block.wgsl`, lit like the standard material.
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "9a41d7c2-5e3b-4c8f-a6d0-2b7e1f4c9d83"]
+pub struct BlockMaterial {
+    #[uniform(0)]
+    surfaces: Surfaces,
+    #[texture(1)]
+    #[sampler(2)]
+    atlas: Option<Handle<Image>>,
+    alpha_mode: AlphaMode,
+}
+
+impl Material for BlockMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/block.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/block.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            ATTRIBUTE_BLOCK_TYPE.at_shader_location(3),
+            ATTRIBUTE_ATLAS_RECT.at_shader_location(4),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// The two variants of the block material, since transparent blocks need blending.
+pub struct BlockMaterials {
+    pub opaque: Handle<BlockMaterial>,
+    pub transparent: Handle<BlockMaterial>,
+}
+
+impl FromWorld for BlockMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let registry = world.resource::<BlockRegistry>();
+        let standard_materials = world.resource::<Assets<StandardMaterial>>();
+
+        // Same surfaces as the regular block materials, which the other tools still use.
+        let mut surfaces = Surfaces {
+            surfaces: [Surface::default(); PALETTE_SIZE],
+        };
+        for block_type in registry.types().take(PALETTE_SIZE) {
+            let material = registry
+                .get(block_type)
+                .and_then(|definition| standard_materials.get(&definition.material));
+            let color = registry.face_color(block_type, standard_materials);
+
+            if let (Some(material), Some(color)) = (material, color) {
+                surfaces.surfaces[block_type.0 as usize] = Surface {
+                    color: Vec4::from(color.as_linear_rgba_f32()),
+                    emissive: Vec4::from(material.emissive.as_linear_rgba_f32()),
+                    roughness: material.perceptual_roughness,
+                };
+            }
+        }
+
+        let atlas = registry.atlas().cloned();
+        let mut materials = world.resource_mut::<Assets<BlockMaterial>>();
+
+        BlockMaterials {
+            opaque: materials.add(BlockMaterial {
+                surfaces: surfaces.clone(),
+                atlas: atlas.clone(),
+                alpha_mode: AlphaMode::Opaque,
+            }),
+            transparent: materials.add(BlockMaterial {
+                surfaces,
+                atlas,
+                alpha_mode: AlphaMode::Blend,
+            }),
+        }
+    }
+}
+
+/// Material of the chunk meshes, unless `--voxel-shader` replaces it.
+pub struct BlockMaterialPlugin;
+
+impl Plugin for BlockMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        if !voxel_material::enabled() {
+            app.add_plugin(MaterialPlugin::<BlockMaterial>::default())
+                .init_resource::<BlockMaterials>();
+        }
+    }
+}
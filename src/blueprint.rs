@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::input_map::{Action, InputMap};
+use crate::{cli_arg, compression, BlockPosition, FloorTile};
+
+/// A design to recreate, loaded from a RON file listing the cells that should hold a block. The file
+/// may be compressed with zstd.
+///
+/// Start the game with `--blueprint <path>` to overlay one.
+#[derive(Deserialize)]
+pub struct Blueprint {
+    pub cells: HashSet<(i64, i64, i64)>,
+}
+
+impl Blueprint {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = compression::read_to_string(path)?;
+        let blueprint = ron::from_str(&content)?;
+        Ok(blueprint)
+    }
+}
+
+struct BlueprintAssets {
+    mesh: Handle<Mesh>,
+    missing: Handle<StandardMaterial>,
+    misplaced: Handle<StandardMaterial>,
+}
+
+/// Whether the overlay is shown, toggled with `Action::ToggleBlueprint`.
+struct BlueprintOverlay {
+    visible: bool,
+}
+
+/// Ghost of a blueprint cell, hidden once a block fills it.
+#[derive(Component)]
+struct BlueprintGhost((i64, i64, i64));
+
+/// Highlights a block that is not part of the blueprint.
+#[derive(Component)]
+struct MisplacedMarker;
+
+fn setup_blueprint(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    blueprint: Res<Blueprint>,
+) {
+    let assets = BlueprintAssets {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        missing: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.2, 0.5, 1.0, 0.3),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        misplaced: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 0.1, 0.1, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    };
+
+    for &(x, y, z) in &blueprint.cells {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.missing.clone(),
+                transform: BlockPosition { x, y, z }.into_transform(),
+                ..default()
+            })
+            .insert(BlueprintGhost((x, y, z)));
+    }
+
+    commands.insert_resource(assets);
+}
+
+fn toggle_blueprint_overlay(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut overlay: ResMut<BlueprintOverlay>,
+) {
+    if input_map.just_pressed(Action::ToggleBlueprint, &keys, &mouse) {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+/// Diff the world against the blueprint whenever blocks are added or removed.
+fn check_blueprint(
+    mut commands: Commands,
+    blueprint: Res<Blueprint>,
+    assets: Res<BlueprintAssets>,
+    overlay: Res<BlueprintOverlay>,
+    added: Query<(), (Added<BlockPosition>, Without<FloorTile>)>,
+    removed: RemovedComponents<BlockPosition>,
+    blocks: Query<&BlockPosition, Without<FloorTile>>,
+    mut ghosts: Query<(&BlueprintGhost, &mut Visibility)>,
+    markers: Query<Entity, With<MisplacedMarker>>,
+) {
+    if added.is_empty() && removed.iter().next().is_none() && !overlay.is_changed() {
+        return;
+    }
+
+    let _span = info_span!("blueprint_diff").entered();
+
+    let placed: HashSet<(i64, i64, i64)> = blocks.iter().map(|p| (p.x, p.y, p.z)).collect();
+
+    for (ghost, mut visibility) in &mut ghosts {
+        visibility.is_visible = overlay.visible && !placed.contains(&ghost.0);
+    }
+
+    for marker in &markers {
+        commands.entity(marker).despawn();
+    }
+
+    if !overlay.visible {
+        return;
+    }
+
+    for &(x, y, z) in placed.difference(&blueprint.cells) {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.misplaced.clone(),
+                // Slightly bigger than a block so it wraps around it.
+                transform: BlockPosition { x, y, z }
+                    .into_transform()
+                    .with_scale(Vec3::splat(1.05)),
+                ..default()
+            })
+            .insert(MisplacedMarker);
+    }
+}
+
+/// Overlays a blueprint as ghost blocks, highlighting missing and misplaced blocks.
+pub struct BlueprintPlugin;
+
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        let path = match cli_arg("--blueprint") {
+            Some(path) => path,
+            None => return,
+        };
+
+        let blueprint = match Blueprint::load(&path) {
+            Ok(blueprint) => blueprint,
+            Err(err) => {
+                error!("Could not load blueprint {}: {}", path, err);
+                return;
+            }
+        };
+
+        app.insert_resource(blueprint)
+            .insert_resource(BlueprintOverlay { visible: true })
+            .add_startup_system(setup_blueprint)
+            .add_system(toggle_blueprint_overlay)
+            .add_system(check_blueprint.after(toggle_blueprint_overlay));
+    }
+}
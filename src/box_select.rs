@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockMap, BlockRegistry, SelectedBlock};
+use crate::chunk::Chunks;
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{BlockPosition, MyRaycastSet};
+
+/// What clicking in the world does, switched with `Action::ToggleBoxSelect`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ToolMode {
+    /// Place and remove single blocks.
+    Build,
+    /// Pick the corners of a box to fill, clear or replace at once.
+    BoxSelect,
+    /// Paste copied blocks.
+    Paste,
+    /// Repaint connected blocks of a same type.
+    Bucket,
+}
+
+impl Default for ToolMode {
+    fn default() -> Self {
+        ToolMode::Build
+    }
+}
+
+/// Corners of the box picked in `ToolMode::BoxSelect`, both included.
+#[derive(Default)]
+pub struct Selection {
+    pub first: Option<BlockPosition>,
+    pub second: Option<BlockPosition>,
+}
+
+impl Selection {
+    /// Lowest and highest cells of the box, once both corners are picked.
+    pub fn bounds(&self) -> Option<(BlockPosition, BlockPosition)> {
+        Some(box_bounds(self.first?, self.second?))
+    }
+
+    /// Every cell of the box, bottom layer first.
+    pub fn cells(&self) -> impl Iterator<Item = BlockPosition> {
+        self.bounds().into_iter().flat_map(|(min, max)| {
+            (min.y..=max.y).flat_map(move |y| {
+                (min.z..=max.z)
+                    .flat_map(move |z| (min.x..=max.x).map(move |x| BlockPosition { x, y, z }))
+            })
+        })
+    }
+}
+
+fn box_bounds(a: BlockPosition, b: BlockPosition) -> (BlockPosition, BlockPosition) {
+    (
+        BlockPosition {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+            z: a.z.min(b.z),
+        },
+        BlockPosition {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+            z: a.z.max(b.z),
+        },
+    )
+}
+
+/// Edges of the selected box.
+#[derive(Component)]
+struct SelectionBox;
+
+/// The twelve edges of a unit cube centered on the origin, scaled to the box by its transform.
+fn wireframe_cube() -> Mesh {
+    let corners: Vec<[f32; 3]> = (0..8)
+        .map(|i| {
+            let coordinate = |bit: u32| if i & bit == 0 { -0.5 } else { 0.5 };
+            [coordinate(1), coordinate(2), coordinate(4)]
+        })
+        .collect();
+    // Pairs of corners differing on a single axis.
+    let mut edges = Vec::new();
+    for i in 0..8u32 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                edges.extend([i, i | bit]);
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; corners.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; corners.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, corners);
+    mesh.set_indices(Some(Indices::U32(edges)));
+    mesh
+}
+
+fn setup_selection_box(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(wireframe_cube()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.2, 0.9, 1.0),
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(SelectionBox);
+}
+
+fn toggle_tool_mode(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut mode: ResMut<ToolMode>,
+    mut selection: ResMut<Selection>,
+) {
+    if !input_map.just_pressed(Action::ToggleBoxSelect, &keys, &mouse) {
+        return;
+    }
+
+    *mode = match *mode {
+        ToolMode::BoxSelect => ToolMode::Build,
+        _ => ToolMode::BoxSelect,
+    };
+    *selection = Selection::default();
+
+    info!(
+        "Box selection {}",
+        if *mode == ToolMode::BoxSelect {
+            "on"
+        } else {
+            "off"
+        }
+    );
+}
+
+/// Cell of the box corner under the cursor.
+fn pointed_corner(pointed: &PointedFace) -> Option<BlockPosition> {
+    let cell = pointed.0?.cell;
+
+    // Clicking the floor picks the layer above it, since floor tiles aren't part of the build.
+    Some(BlockPosition {
+        y: cell.y.max(1),
+        ..cell
+    })
+}
+
+/// Each click picks a corner, the third one starting a new box.
+fn set_box_corner(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mode: Res<ToolMode>,
+    mut selection: ResMut<Selection>,
+    pointed: Res<PointedFace>,
+) {
+    if *mode != ToolMode::BoxSelect || !input_map.just_pressed(Action::SetBoxCorner, &keys, &mouse)
+    {
+        return;
+    }
+
+    let corner = match pointed_corner(&pointed) {
+        Some(corner) => corner,
+        None => return,
+    };
+
+    if selection.first.is_none() || selection.second.is_some() {
+        selection.first = Some(corner);
+        selection.second = None;
+    } else {
+        selection.second = Some(corner);
+    }
+}
+
+/// Fills the empty cells of the box with the selected block type, clears it, or turns every block
+/// in it into the selected type, as a single undo step.
+fn apply_box_operation(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    map: Res<BlockMap>,
+    chunks: Res<Chunks>,
+    selection: Res<Selection>,
+    mut edits: EventWriter<EditBlocks>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    let pressed = |action| input_map.just_pressed(action, &keys, &mouse);
+    let (fill, clear, replace) = (
+        pressed(Action::FillBox),
+        pressed(Action::ClearBox),
+        pressed(Action::ReplaceBox),
+    );
+
+    if !(fill || clear || replace) || selection.bounds().is_none() {
+        return;
+    }
+
+    let _span = info_span!("apply_box_operation").entered();
+
+    let mut batch = Vec::new();
+
+    if fill {
+        // Layers are filled bottom up, so blocks needing support can rest on the layer below.
+        let mut filled = HashSet::new();
+        for position in selection.cells() {
+            let below = BlockPosition {
+                y: position.y - 1,
+                ..position
+            };
+            let supported =
+                filled.contains(&below) && position.in_bounds() && !map.contains(position);
+
+            if supported || registry.can_place(selected.0, position, &map) {
+                filled.insert(position);
+                batch.push(BlockEdit::Place {
+                    position,
+                    block_type: selected.0,
+                });
+            }
+        }
+    } else {
+        for position in selection.cells() {
+            let block_type = match chunks.get(position) {
+                Some(block_type) => block_type,
+                None => continue,
+            };
+
+            if clear {
+                batch.push(BlockEdit::Remove { position });
+            } else if block_type != selected.0 {
+                batch.extend([
+                    BlockEdit::Remove { position },
+                    BlockEdit::Place {
+                        position,
+                        block_type: selected.0,
+                    },
+                ]);
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        edits.send(EditBlocks {
+            edits: batch,
+            source: player.get_single().ok(),
+        });
+    }
+}
+
+/// Draws the selected box, or the box from the first corner to the cursor while picking the second.
+fn update_selection_box(
+    mode: Res<ToolMode>,
+    selection: Res<Selection>,
+    pointed: Res<PointedFace>,
+    mut boxes: Query<(&mut Transform, &mut Visibility), With<SelectionBox>>,
+) {
+    let corners = match (selection.first, selection.second) {
+        (Some(first), Some(second)) => Some((first, second)),
+        (Some(first), None) => pointed_corner(&pointed).map(|corner| (first, corner)),
+        _ => None,
+    };
+
+    for (mut transform, mut visibility) in &mut boxes {
+        let (min, max) = match corners.filter(|_| *mode == ToolMode::BoxSelect) {
+            Some((a, b)) => box_bounds(a, b),
+            None => {
+                visibility.is_visible = false;
+                continue;
+            }
+        };
+
+        let (min, max) = (
+            min.into_transform().translation,
+            max.into_transform().translation,
+        );
+        visibility.is_visible = true;
+        transform.translation = (min + max) / 2.0;
+        // Slightly bigger than the cells, so edges aren't hidden inside the blocks' faces.
+        transform.scale = max - min + Vec3::splat(1.02);
+    }
+}
+
+/// Tool picking an axis-aligned box of cells to fill, clear or replace at once.
+pub struct BoxSelectPlugin;
+
+impl Plugin for BoxSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ToolMode>()
+            .init_resource::<Selection>()
+            .add_startup_system(setup_selection_box)
+            .add_system(toggle_tool_mode)
+            .add_system(set_box_corner.after(toggle_tool_mode))
+            .add_system(apply_box_operation.after(set_box_corner))
+            .add_system(update_selection_box.after(set_box_corner));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockMap, SelectedBlock};
+use crate::box_select::ToolMode;
+use crate::chunk::Chunks;
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::repeat_placement::face_direction;
+use crate::voxel_raycast::PointedFace;
+use crate::{cli_arg, BlockPosition, MyRaycastSet};
+
+/// Most blocks changed by one fill, unless set with `--bucket-limit <count>`.
+const DEFAULT_FILL_LIMIT: usize = 4096;
+
+/// Fills larger than this are cancelled rather than cut, so a click on a wall doesn't repaint
+/// the whole world.
+struct FillLimit(usize);
+
+/// Blocks of the same type as `start` connected to it through their faces, `start` included.
+///
+/// `plane` is the unit normal of the plane to stay on, or None to follow every face. Returns None
+/// if there are more than `limit`.
+fn connected_same_type(
+    map: &BlockMap,
+    chunks: &Chunks,
+    start: BlockPosition,
+    plane: Option<(i64, i64, i64)>,
+    limit: usize,
+) -> Option<Vec<BlockPosition>> {
+    let block_type = chunks.get(start)?;
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut connected = Vec::new();
+
+    while let Some(position) = queue.pop_front() {
+        connected.push(position);
+        if connected.len() > limit {
+            return None;
+        }
+
+        for (neighbour, _) in map.neighbours(position) {
+            let in_plane = plane.map_or(true, |(dx, dy, dz)| {
+                (neighbour.x - position.x) * dx
+                    + (neighbour.y - position.y) * dy
+                    + (neighbour.z - position.z) * dz
+                    == 0
+            });
+
+            if in_plane && chunks.get(neighbour) == Some(block_type) && visited.insert(neighbour) {
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    Some(connected)
+}
+
+fn toggle_bucket(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut mode: ResMut<ToolMode>,
+) {
+    if !input_map.just_pressed(Action::ToggleBucket, &keys, &mouse) {
+        return;
+    }
+
+    *mode = match *mode {
+        ToolMode::Bucket => ToolMode::Build,
+        _ => ToolMode::Bucket,
+    };
+
+    info!(
+        "Bucket {}",
+        if *mode == ToolMode::Bucket {
+            "on"
+        } else {
+            "off"
+        }
+    );
+}
+
+/// Turns the blocks connected to the clicked one on the plane of the clicked face into the
+/// selected type, or the whole connected volume with `Action::BucketFillVolume`.
+fn bucket_fill(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mode: Res<ToolMode>,
+    selected: Res<SelectedBlock>,
+    limit: Res<FillLimit>,
+    map: Res<BlockMap>,
+    chunks: Res<Chunks>,
+    mut edits: EventWriter<EditBlocks>,
+    pointed: Res<PointedFace>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    if *mode != ToolMode::Bucket {
+        return;
+    }
+
+    let volume = input_map.just_pressed(Action::BucketFillVolume, &keys, &mouse);
+    if !volume && !input_map.just_pressed(Action::BucketFill, &keys, &mouse) {
+        return;
+    }
+
+    let hit = match pointed.0 {
+        Some(hit) => hit,
+        None => return,
+    };
+
+    // Floor tiles have no type to replace.
+    let start = hit.cell;
+    match chunks.get(start) {
+        Some(block_type) if block_type != selected.0 => {}
+        _ => return,
+    }
+
+    let _span = info_span!("bucket_fill").entered();
+
+    let plane = (!volume).then(|| face_direction(hit.normal));
+    let cells = match connected_same_type(&map, &chunks, start, plane, limit.0) {
+        Some(cells) => cells,
+        None => {
+            warn!("Fill cancelled, more than {} blocks to change", limit.0);
+            return;
+        }
+    };
+
+    let block_type = selected.0;
+    edits.send(EditBlocks {
+        edits: cells
+            .into_iter()
+            .flat_map(|position| {
+                [
+                    BlockEdit::Remove { position },
+                    BlockEdit::Place {
+                        position,
+                        block_type,
+                    },
+                ]
+            })
+            .collect(),
+        source: player.get_single().ok(),
+    });
+}
+
+/// Paint bucket repainting connected blocks of a same type, toggled with Q.
+pub struct BucketPlugin;
+
+impl Plugin for BucketPlugin {
+    fn build(&self, app: &mut App) {
+        let limit = cli_arg("--bucket-limit")
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_FILL_LIMIT);
+
+        app.insert_resource(FillLimit(limit))
+            .add_system(toggle_bucket)
+            .add_system(bucket_fill.after(toggle_bucket));
+    }
+}
@@ -1,15 +1,44 @@
 use bevy::{
     input::mouse::{MouseMotion, MouseWheel},
     prelude::*,
-    render::camera::Projection,
+    render::camera::{PerspectiveProjection, Projection},
 };
 
-use bevy_mod_raycast::RayCastSource;
+use bevy_mod_raycast::{RayCastMethod, RayCastSource};
 
+use crate::input_map::{Action, InputMap};
 use crate::MyRaycastSet;
 
 // Copied from : https://bevy-cheatbook.github.io/cookbook/pan-orbit-camera.html
 
+/// Seconds taken to fly the focus point to a new position.
+const FLIGHT_DURATION: f32 = 0.8;
+/// Speed of the fly camera, in blocks per second.
+const FLY_SPEED: f32 = 8.0;
+/// Radians turned per pixel of mouse movement in fly mode.
+const LOOK_SENSITIVITY: f32 = 0.003;
+/// Pitch limit in fly mode, just short of straight up or down.
+const MAX_PITCH: f32 = 1.54;
+
+/// How the camera is controlled, switched with `Action::ToggleFlyCamera`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Orbit around a focus point, picking blocks under the cursor.
+    Orbit,
+    /// First-person free flight with mouse look, picking blocks under the crosshair.
+    Fly,
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::Orbit
+    }
+}
+
+/// Marks the point picked in fly mode, at the center of the screen.
+#[derive(Component)]
+struct Crosshair;
+
 /// Tags an entity as capable of panning and orbiting.
 #[derive(Component)]
 struct PanOrbitCamera {
@@ -29,28 +58,65 @@ impl Default for PanOrbitCamera {
     }
 }
 
-/// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with right mouse click.
+/// Event flying the orbit focus point to the given position, keeping the current angle and zoom.
+pub struct FocusCamera(pub Vec3);
+
+/// Comfort options for users sensitive to motion, enabled with `--reduced-motion`.
+#[derive(Default)]
+pub struct CameraComfort {
+    /// Maximum orbit speed in radians per second, on each axis.
+    pub max_orbit_speed: Option<f32>,
+    /// Maximum vertical field of view in radians, for perspective projections.
+    pub max_fov: Option<f32>,
+    /// Jump straight to the new focus point instead of flying there.
+    pub instant_focus: bool,
+}
+
+/// Focus point flight started by a `FocusCamera` event.
+struct CameraFlight {
+    from: Vec3,
+    to: Vec3,
+    elapsed: f32,
+}
+
+impl CameraComfort {
+    fn reduced_motion() -> Self {
+        CameraComfort {
+            max_orbit_speed: Some(std::f32::consts::PI),
+            max_fov: Some(60.0_f32.to_radians()),
+            instant_focus: true,
+        }
+    }
+}
+
+/// Orbit around the focus point with middle mouse drag, pan with Shift+middle mouse drag, zoom with
+/// scroll wheel.
 fn pan_orbit_camera(
     windows: Res<Windows>,
+    time: Res<Time>,
+    comfort: Res<CameraComfort>,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     input_mouse: Res<Input<MouseButton>>,
+    input_keys: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mode: Res<CameraMode>,
     mut query: Query<(&mut PanOrbitCamera, &mut Transform, &Projection)>,
 ) {
-    // change input mapping for orbit and panning here
-    let orbit_button = MouseButton::Right;
-    let pan_button = MouseButton::Middle;
+    if *mode != CameraMode::Orbit {
+        return;
+    }
 
     let mut pan = Vec2::ZERO;
     let mut rotation_move = Vec2::ZERO;
     let mut scroll = 0.0;
     let mut orbit_button_changed = false;
 
-    if input_mouse.pressed(orbit_button) {
+    if input_map.pressed(Action::Orbit, &input_keys, &input_mouse) {
         for ev in ev_motion.iter() {
             rotation_move += ev.delta;
         }
-    } else if input_mouse.pressed(pan_button) {
+    } else if input_map.pressed(Action::Pan, &input_keys, &input_mouse) {
         // Pan only if we're not rotating at the moment
         for ev in ev_motion.iter() {
             pan += ev.delta;
@@ -59,7 +125,13 @@ fn pan_orbit_camera(
     for ev in ev_scroll.iter() {
         scroll += ev.y;
     }
-    if input_mouse.just_released(orbit_button) || input_mouse.just_pressed(orbit_button) {
+    // Scrolling while holding it cycles the hotbar instead.
+    if input_map.pressed(Action::CycleHotbar, &input_keys, &input_mouse) {
+        scroll = 0.0;
+    }
+    if input_map.just_released(Action::Orbit, &input_keys, &input_mouse)
+        || input_map.just_pressed(Action::Orbit, &input_keys, &input_mouse)
+    {
         orbit_button_changed = true;
     }
 
@@ -84,6 +156,13 @@ fn pan_orbit_camera(
                 }
             };
             let delta_y = rotation_move.y / window.y * std::f32::consts::PI;
+            let (delta_x, delta_y) = match comfort.max_orbit_speed {
+                Some(max_speed) => {
+                    let max = max_speed * time.delta_seconds();
+                    (delta_x.clamp(-max, max), delta_y.clamp(-max, max))
+                }
+                None => (delta_x, delta_y),
+            };
             let yaw = Quat::from_rotation_y(-delta_x);
             let pitch = Quat::from_rotation_x(-delta_y);
             transform.rotation = yaw * transform.rotation; // rotate around global y axis
@@ -121,6 +200,156 @@ fn pan_orbit_camera(
     }
 }
 
+fn limit_fov(comfort: Res<CameraComfort>, mut query: Query<&mut Projection, With<PanOrbitCamera>>) {
+    let max_fov = match comfort.max_fov {
+        Some(max_fov) => max_fov,
+        None => return,
+    };
+
+    for mut projection in &mut query {
+        // Only write when needed so the projection isn't flagged as changed every frame.
+        let limited = match &*projection {
+            Projection::Perspective(perspective) if perspective.fov > max_fov => {
+                PerspectiveProjection {
+                    fov: max_fov,
+                    ..perspective.clone()
+                }
+            }
+            _ => continue,
+        };
+        *projection = Projection::Perspective(limited);
+    }
+}
+
+fn focus_camera(
+    time: Res<Time>,
+    comfort: Res<CameraComfort>,
+    mut events: EventReader<FocusCamera>,
+    mut flight: Local<Option<CameraFlight>>,
+    mut query: Query<(&mut PanOrbitCamera, &mut Transform)>,
+) {
+    if let Some(event) = events.iter().last() {
+        let from = query.iter().next().map_or(event.0, |(p, _)| p.focus);
+        *flight = Some(CameraFlight {
+            from,
+            to: event.0,
+            elapsed: 0.0,
+        });
+    }
+
+    let current = match flight.as_mut() {
+        Some(current) => current,
+        None => return,
+    };
+
+    current.elapsed += time.delta_seconds();
+    let t = if comfort.instant_focus {
+        1.0
+    } else {
+        (current.elapsed / FLIGHT_DURATION).min(1.0)
+    };
+    // Smoothstep, to ease in and out of the flight.
+    let focus = current.from.lerp(current.to, t * t * (3.0 - 2.0 * t));
+
+    if t >= 1.0 {
+        *flight = None;
+    }
+
+    for (mut pan_orbit, mut transform) in query.iter_mut() {
+        pan_orbit.focus = focus;
+        let rot_matrix = Mat3::from_quat(transform.rotation);
+        transform.translation =
+            pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+    }
+}
+
+/// Switches between orbit and fly mode. The cursor is locked while flying, and blocks are picked
+/// along the view direction instead of under the cursor.
+fn toggle_fly_camera(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut mode: ResMut<CameraMode>,
+    mut windows: ResMut<Windows>,
+    mut cameras: Query<(
+        &mut PanOrbitCamera,
+        &Transform,
+        &mut RayCastSource<MyRaycastSet>,
+    )>,
+    mut crosshair: Query<&mut Visibility, With<Crosshair>>,
+) {
+    if !input_map.just_pressed(Action::ToggleFlyCamera, &keys, &mouse) {
+        return;
+    }
+
+    *mode = match *mode {
+        CameraMode::Orbit => CameraMode::Fly,
+        CameraMode::Fly => CameraMode::Orbit,
+    };
+    let flying = *mode == CameraMode::Fly;
+
+    let cursor = windows.get_primary_mut().and_then(|window| {
+        window.set_cursor_lock_mode(flying);
+        window.set_cursor_visibility(!flying);
+        window.cursor_position()
+    });
+
+    for (mut pan_orbit, transform, mut source) in &mut cameras {
+        if flying {
+            source.cast_method = RayCastMethod::Transform;
+        } else {
+            // Orbit around what is in front of the camera, so the view doesn't jump.
+            pan_orbit.focus = transform.translation + transform.forward() * pan_orbit.radius;
+            if let Some(cursor) = cursor {
+                source.cast_method = RayCastMethod::Screenspace(cursor);
+            }
+        }
+    }
+
+    for mut visibility in &mut crosshair {
+        visibility.is_visible = flying;
+    }
+}
+
+/// Mouse look, and flight along the view direction, with vertical moves along the world axis.
+fn fly_camera(
+    time: Res<Time>,
+    comfort: Res<CameraComfort>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mode: Res<CameraMode>,
+    mut motion: EventReader<MouseMotion>,
+    mut query: Query<&mut Transform, With<PanOrbitCamera>>,
+) {
+    if *mode != CameraMode::Fly {
+        return;
+    }
+
+    let look: Vec2 = motion.iter().map(|event| event.delta).sum::<Vec2>() * LOOK_SENSITIVITY;
+    let look = match comfort.max_orbit_speed {
+        Some(max_speed) => look.clamp_length_max(max_speed * time.delta_seconds()),
+        None => look,
+    };
+
+    let axis = |positive: Action, negative: Action| {
+        input_map.pressed(positive, &keys, &mouse) as i32 as f32
+            - input_map.pressed(negative, &keys, &mouse) as i32 as f32
+    };
+    let forward = axis(Action::MoveForward, Action::MoveBackward);
+    let right = axis(Action::MoveRight, Action::MoveLeft);
+    let up = axis(Action::FlyUp, Action::FlyDown);
+
+    for mut transform in &mut query {
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let pitch = (pitch - look.y).clamp(-MAX_PITCH, MAX_PITCH);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw - look.x, pitch, 0.0);
+
+        let direction = transform.forward() * forward + transform.right() * right + Vec3::Y * up;
+        transform.translation += direction.normalize_or_zero() * FLY_SPEED * time.delta_seconds();
+    }
+}
+
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
     let window = Vec2::new(window.width() as f32, window.height() as f32);
@@ -144,11 +373,56 @@ fn spawn_camera(mut commands: Commands) {
         .insert(RayCastSource::<MyRaycastSet>::new()); // Designate the camera as the source of raycasting
 }
 
+fn spawn_crosshair(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    // Only shown in fly mode.
+                    visibility: Visibility { is_visible: false },
+                    ..TextBundle::from_section(
+                        "+",
+                        TextStyle {
+                            font: asset_server.load("fonts/DejaVuSans.ttf"),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                })
+                .insert(Crosshair);
+        });
+}
+
 pub struct GameCameraPlugin;
 
 impl Plugin for GameCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_camera)
-            .add_system(pan_orbit_camera);
+        let comfort = if std::env::args().any(|arg| arg == "--reduced-motion") {
+            CameraComfort::reduced_motion()
+        } else {
+            CameraComfort::default()
+        };
+
+        app.insert_resource(comfort)
+            .init_resource::<CameraMode>()
+            .add_event::<FocusCamera>()
+            .add_startup_system(spawn_camera)
+            .add_startup_system(spawn_crosshair)
+            .add_system(toggle_fly_camera)
+            .add_system(fly_camera.after(toggle_fly_camera))
+            .add_system(pan_orbit_camera.after(toggle_fly_camera))
+            .add_system(focus_camera.after(pan_orbit_camera))
+            .add_system(limit_fov);
     }
 }
@@ -0,0 +1,123 @@
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::chunk::Chunks;
+use crate::{cli_arg, spawn_cube, BlockPosition, WORLD_LIMIT};
+
+/// Height of the tallest column, in blocks.
+const MAX_HEIGHT: i64 = 32;
+
+/// Block heights generated from a CSV table, indexed by row then column.
+///
+/// Start the game with `--chart <path>` to build one. With `--chart-column <name>`, the column is
+/// drawn as a bar chart with a bar per row. Without it, the whole table must be numbers and is
+/// drawn as a heightfield.
+pub struct Chart {
+    pub heights: Vec<Vec<i64>>,
+}
+
+impl Chart {
+    pub fn load(path: &str, column: Option<&str>) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let rows: Vec<Vec<&str>> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split(',').map(str::trim).collect())
+            .collect();
+
+        let values: Vec<Vec<f64>> = match column {
+            Some(name) => {
+                let header = rows.first().ok_or("empty table")?;
+                let index = header
+                    .iter()
+                    .position(|h| *h == name)
+                    .ok_or_else(|| format!("no column named {}", name))?;
+
+                rows[1..]
+                    .iter()
+                    .map(|row| parse_value(row.get(index).copied().unwrap_or_default()))
+                    .map(|value| value.map(|value| vec![value]))
+                    .collect::<Result<_, String>>()?
+            }
+            None => rows
+                .iter()
+                .map(|row| row.iter().map(|cell| parse_value(cell)).collect())
+                .collect::<Result<_, String>>()?,
+        };
+
+        let max = values.iter().flatten().copied().fold(0.0, f64::max);
+        if max <= 0.0 {
+            return Err("no positive value to draw".to_string());
+        }
+
+        let heights = values
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| (value / max * MAX_HEIGHT as f64).round().max(0.0) as i64)
+                    .collect()
+            })
+            .collect();
+
+        Ok(Chart { heights })
+    }
+}
+
+fn parse_value(cell: &str) -> Result<f64, String> {
+    cell.parse()
+        .map_err(|_| format!("{:?} is not a number", cell))
+}
+
+/// Stacks the columns of the chart on the floor, starting from the origin.
+fn build_chart(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    mut chunks: ResMut<Chunks>,
+    chart: Res<Chart>,
+) {
+    for (x, row) in chart.heights.iter().enumerate() {
+        for (z, &height) in row.iter().enumerate() {
+            for y in 1..=height.min(WORLD_LIMIT) {
+                let position = BlockPosition {
+                    x: x as i64,
+                    y,
+                    z: z as i64,
+                };
+
+                if position.in_bounds() {
+                    spawn_cube(
+                        &mut commands,
+                        &registry,
+                        &mut chunks,
+                        position,
+                        BlockType::STONE,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Turns a CSV table into a block bar chart or heightfield.
+pub struct ChartPlugin;
+
+impl Plugin for ChartPlugin {
+    fn build(&self, app: &mut App) {
+        let path = match cli_arg("--chart") {
+            Some(path) => path,
+            None => return,
+        };
+
+        let chart = match Chart::load(&path, cli_arg("--chart-column").as_deref()) {
+            Ok(chart) => chart,
+            Err(err) => {
+                error!("Could not load chart {}: {}", path, err);
+                return;
+            }
+        };
+
+        app.insert_resource(chart).add_startup_system(build_chart);
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::block_material::BlockMaterials;
+use crate::mesher::{ChunkMesher, ChunkMeshes};
+use crate::voxel_material::VoxelMaterials;
+use crate::BlockPosition;
+
+/// Blocks along each side of a chunk.
+pub const CHUNK_SIZE: i64 = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ChunkPosition {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl ChunkPosition {
+    /// The chunk holding `position`, and the index of the block within it.
+    pub fn of(position: BlockPosition) -> (ChunkPosition, usize) {
+        let chunk = ChunkPosition {
+            x: position.x.div_euclid(CHUNK_SIZE),
+            y: position.y.div_euclid(CHUNK_SIZE),
+            z: position.z.div_euclid(CHUNK_SIZE),
+        };
+        let (x, y, z) = (
+            position.x.rem_euclid(CHUNK_SIZE),
+            position.y.rem_euclid(CHUNK_SIZE),
+            position.z.rem_euclid(CHUNK_SIZE),
+        );
+
+        (chunk, ((y * CHUNK_SIZE + z) * CHUNK_SIZE + x) as usize)
+    }
+
+    /// The block at the corner of the chunk, that its mesh is relative to.
+    pub fn corner(self) -> BlockPosition {
+        BlockPosition {
+            x: self.x * CHUNK_SIZE,
+            y: self.y * CHUNK_SIZE,
+            z: self.z * CHUNK_SIZE,
+        }
+    }
+}
+
+/// Types of the blocks of a chunk, and the entity rendering them, whose children hold the meshes.
+struct Chunk {
+    blocks: Box<[Option<BlockType>; CHUNK_VOLUME]>,
+    entity: Option<Entity>,
+    /// Set when blocks changed since the chunk was last meshed.
+    dirty: bool,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            blocks: Box::new([None; CHUNK_VOLUME]),
+            entity: None,
+            dirty: false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.iter().all(Option::is_none)
+    }
+}
+
+/// Block types of the world, stored by chunks of `CHUNK_SIZE` cubed blocks.
+///
+/// Blocks still have an entity each, for their own components like lights or selection, but they
+/// are drawn through the mesh of their chunk.
+#[derive(Default)]
+pub struct Chunks {
+    chunks: HashMap<ChunkPosition, Chunk>,
+    /// Chunks changed since the last save, so autosaves only write those.
+    unsaved: HashSet<ChunkPosition>,
+}
+
+impl Chunks {
+    pub fn get(&self, position: BlockPosition) -> Option<BlockType> {
+        let (chunk, index) = ChunkPosition::of(position);
+        self.chunks.get(&chunk)?.blocks[index]
+    }
+
+    /// Changes the block at `position` and marks its chunk for meshing.
+    pub fn set(&mut self, position: BlockPosition, block_type: Option<BlockType>) {
+        let (chunk_position, index) = ChunkPosition::of(position);
+
+        // Clearing a block of a missing chunk doesn't need to create it.
+        if block_type.is_none() && !self.chunks.contains_key(&chunk_position) {
+            return;
+        }
+        let chunk = self.chunks.entry(chunk_position).or_insert_with(Chunk::new);
+
+        if chunk.blocks[index] == block_type {
+            return;
+        }
+        chunk.blocks[index] = block_type;
+        chunk.dirty = true;
+        self.unsaved.insert(chunk_position);
+
+        // A block on the border may hide or uncover faces of the neighbouring chunk.
+        let local = [
+            position.x.rem_euclid(CHUNK_SIZE),
+            position.y.rem_euclid(CHUNK_SIZE),
+            position.z.rem_euclid(CHUNK_SIZE),
+        ];
+        for (axis, &coordinate) in local.iter().enumerate() {
+            let step = match coordinate {
+                0 => -1,
+                _ if coordinate == CHUNK_SIZE - 1 => 1,
+                _ => continue,
+            };
+
+            let mut neighbour = chunk_position;
+            match axis {
+                0 => neighbour.x += step,
+                1 => neighbour.y += step,
+                _ => neighbour.z += step,
+            }
+
+            if let Some(chunk) = self.chunks.get_mut(&neighbour) {
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    /// The blocks of a chunk, none if it is empty or missing.
+    pub fn blocks_in(
+        &self,
+        chunk_position: ChunkPosition,
+    ) -> impl Iterator<Item = (BlockPosition, BlockType)> + '_ {
+        let corner = chunk_position.corner();
+
+        self.chunks
+            .get(&chunk_position)
+            .into_iter()
+            .flat_map(|chunk| chunk.blocks.iter().enumerate())
+            .filter_map(move |(index, block_type)| {
+                let index = index as i64;
+                let position = BlockPosition {
+                    x: corner.x + index % CHUNK_SIZE,
+                    y: corner.y + index / (CHUNK_SIZE * CHUNK_SIZE),
+                    z: corner.z + index / CHUNK_SIZE % CHUNK_SIZE,
+                };
+                Some((position, (*block_type)?))
+            })
+    }
+
+    /// Every chunk holding blocks, or emptied since it was last meshed.
+    pub fn positions(&self) -> impl Iterator<Item = ChunkPosition> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// The opaque and transparent meshes of a chunk, relative to its corner, culled against the
+    /// neighbouring chunks.
+    pub fn mesh(&self, mesher: &ChunkMesher, position: ChunkPosition) -> ChunkMeshes {
+        let corner = position.corner();
+        mesher.mesh(|[x, y, z]| {
+            self.get(BlockPosition {
+                x: corner.x + x,
+                y: corner.y + y,
+                z: corner.z + z,
+            })
+        })
+    }
+
+    pub fn unsaved(&self) -> impl Iterator<Item = ChunkPosition> + '_ {
+        self.unsaved.iter().copied()
+    }
+
+    pub fn has_unsaved(&self) -> bool {
+        !self.unsaved.is_empty()
+    }
+
+    pub fn clear_unsaved(&mut self) {
+        self.unsaved.clear();
+    }
+}
+
+/// Rebuilds the meshes of the chunks that changed, and drops the chunks left empty.
+fn mesh_dirty_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<Chunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<BlockRegistry>,
+    block_materials: Option<Res<BlockMaterials>>,
+    voxel_materials: Option<Res<VoxelMaterials>>,
+) {
+    let dirty: Vec<ChunkPosition> = chunks
+        .chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.dirty)
+        .map(|(position, _)| *position)
+        .collect();
+
+    if dirty.is_empty() {
+        return;
+    }
+
+    let _span = info_span!("mesh_dirty_chunks").entered();
+
+    // Only the voxel shader draws ambient occlusion.
+    let mesher = ChunkMesher::new(&registry, voxel_materials.is_some());
+
+    // Meshed before changing any chunk, since faces are culled against the neighbouring ones.
+    let meshed: Vec<_> = dirty
+        .into_iter()
+        .map(|position| (position, chunks.mesh(&mesher, position)))
+        .collect();
+
+    for (position, chunk_meshes) in meshed {
+        let chunk = match chunks.chunks.get_mut(&position) {
+            Some(chunk) => chunk,
+            None => continue,
+        };
+        chunk.dirty = false;
+
+        if let Some(entity) = chunk.entity.take() {
+            commands.entity(entity).despawn_recursive();
+        }
+        if chunk.is_empty() {
+            chunks.chunks.remove(&position);
+            continue;
+        }
+
+        let entity = commands
+            .spawn_bundle(SpatialBundle {
+                transform: position.corner().into_transform(),
+                ..default()
+            })
+            .insert(position)
+            .with_children(|parent| {
+                let layers = [
+                    (chunk_meshes.opaque, false),
+                    (chunk_meshes.transparent, true),
+                ];
+                for (mesh, transparent) in layers {
+                    let mesh = match mesh {
+                        Some(mesh) => meshes.add(mesh),
+                        None => continue,
+                    };
+
+                    if let Some(voxel_materials) = &voxel_materials {
+                        parent.spawn_bundle(MaterialMeshBundle {
+                            mesh,
+                            material: if transparent {
+                                voxel_materials.transparent.clone()
+                            } else {
+                                voxel_materials.opaque.clone()
+                            },
+                            ..default()
+                        });
+                    } else if let Some(block_materials) = &block_materials {
+                        parent.spawn_bundle(MaterialMeshBundle {
+                            mesh,
+                            material: if transparent {
+                                block_materials.transparent.clone()
+                            } else {
+                                block_materials.opaque.clone()
+                            },
+                            ..default()
+                        });
+                    }
+                }
+            })
+            .id();
+        chunk.entity = Some(entity);
+    }
+}
+
+/// Stores blocks in chunks, each drawn with a single mesh per material: one for its opaque blocks,
+/// and one for its transparent blocks if it has any.
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Chunks>().add_system(mesh_dirty_chunks);
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockAssets, BlockMap, BlockRegistry, BlockType};
+use crate::box_select::{Selection, ToolMode};
+use crate::chunk::Chunks;
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{block_position_from_hit, BlockPosition, MyRaycastSet};
+
+/// Blocks copied from a box, relative to its lowest corner.
+struct CopiedBlocks {
+    /// Cells along each axis of the copied box.
+    size: (i64, i64, i64),
+    /// Bottom layer first.
+    blocks: Vec<((i64, i64, i64), BlockType)>,
+}
+
+impl CopiedBlocks {
+    /// The same blocks turned a quarter around Y, still starting at the origin.
+    fn rotated(&self) -> Self {
+        let (width, height, depth) = self.size;
+
+        CopiedBlocks {
+            size: (depth, height, width),
+            blocks: self
+                .blocks
+                .iter()
+                .map(|&((x, y, z), block_type)| ((depth - 1 - z, y, x), block_type))
+                .collect(),
+        }
+    }
+
+    /// Cells and types of the blocks pasted with their lowest corner at `origin`.
+    fn at(&self, origin: BlockPosition) -> impl Iterator<Item = (BlockPosition, BlockType)> + '_ {
+        self.blocks.iter().map(move |&((x, y, z), block_type)| {
+            let position = BlockPosition {
+                x: origin.x + x,
+                y: origin.y + y,
+                z: origin.z + z,
+            };
+            (position, block_type)
+        })
+    }
+}
+
+/// Blocks copied with `Action::Copy`, pasted with `Action::Paste`.
+#[derive(Default)]
+struct Clipboard {
+    copied: Option<CopiedBlocks>,
+}
+
+/// Parent of the ghosts showing where the clipboard would be pasted.
+#[derive(Component)]
+struct PastePreview;
+
+struct PasteMaterial(Handle<StandardMaterial>);
+
+fn setup_paste_preview(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.insert_resource(PasteMaterial(materials.add(StandardMaterial {
+        base_color: Color::rgba(0.2, 0.9, 1.0, 0.3),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    })));
+
+    commands
+        .spawn_bundle(SpatialBundle {
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(PastePreview);
+}
+
+/// Copies the blocks of the selected box.
+fn copy_selection(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    chunks: Res<Chunks>,
+    selection: Res<Selection>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    if !input_map.just_pressed(Action::Copy, &keys, &mouse) {
+        return;
+    }
+
+    let (min, max) = match selection.bounds() {
+        Some(bounds) => bounds,
+        None => return,
+    };
+
+    let blocks: Vec<_> = selection
+        .cells()
+        .filter_map(|position| {
+            let offset = (position.x - min.x, position.y - min.y, position.z - min.z);
+            Some((offset, chunks.get(position)?))
+        })
+        .collect();
+
+    info!("Copied {} blocks", blocks.len());
+    clipboard.copied = Some(CopiedBlocks {
+        size: (max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1),
+        blocks,
+    });
+}
+
+/// Shows the clipboard at the cursor, pasted on every click until `Action::Paste` is pressed again.
+fn toggle_paste(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    clipboard: Res<Clipboard>,
+    mut mode: ResMut<ToolMode>,
+) {
+    if !input_map.just_pressed(Action::Paste, &keys, &mouse) {
+        return;
+    }
+
+    *mode = match *mode {
+        ToolMode::Paste => ToolMode::Build,
+        _ if clipboard.copied.is_some() => ToolMode::Paste,
+        mode => mode,
+    };
+}
+
+fn rotate_paste(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mode: Res<ToolMode>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    if *mode != ToolMode::Paste || !input_map.just_pressed(Action::RotatePaste, &keys, &mouse) {
+        return;
+    }
+
+    if let Some(copied) = &mut clipboard.copied {
+        *copied = copied.rotated();
+    }
+}
+
+/// Cell the clipboard is pasted from, next to the pointed face like a placed block.
+fn paste_origin(pointed: &PointedFace) -> Option<BlockPosition> {
+    let hit = pointed.0?;
+    Some(block_position_from_hit(hit.position, hit.normal))
+}
+
+/// Places the clipboard at the cursor as a single undo step, skipping the cells where the blocks
+/// can't be placed.
+fn commit_paste(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    map: Res<BlockMap>,
+    clipboard: Res<Clipboard>,
+    mode: Res<ToolMode>,
+    mut edits: EventWriter<EditBlocks>,
+    pointed: Res<PointedFace>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    if *mode != ToolMode::Paste || !input_map.just_pressed(Action::CommitPaste, &keys, &mouse) {
+        return;
+    }
+
+    let (copied, origin) = match (&clipboard.copied, paste_origin(&pointed)) {
+        (Some(copied), Some(origin)) => (copied, origin),
+        _ => return,
+    };
+
+    // Blocks resting on pasted ones are placed after them, as copied bottom up.
+    let mut pasted = HashSet::new();
+    let batch: Vec<_> = copied
+        .at(origin)
+        .filter(|&(position, block_type)| {
+            let below = BlockPosition {
+                y: position.y - 1,
+                ..position
+            };
+            let supported =
+                pasted.contains(&below) && position.in_bounds() && !map.contains(position);
+
+            let placeable = supported || registry.can_place(block_type, position, &map);
+            if placeable {
+                pasted.insert(position);
+            }
+            placeable
+        })
+        .map(|(position, block_type)| BlockEdit::Place {
+            position,
+            block_type,
+        })
+        .collect();
+
+    if !batch.is_empty() {
+        edits.send(EditBlocks {
+            edits: batch,
+            source: player.get_single().ok(),
+        });
+    }
+}
+
+/// Rebuilds the ghosts when the clipboard changes, and moves them with the cursor.
+fn update_paste_preview(
+    mut commands: Commands,
+    assets: Res<BlockAssets>,
+    material: Res<PasteMaterial>,
+    mode: Res<ToolMode>,
+    clipboard: Res<Clipboard>,
+    pointed: Res<PointedFace>,
+    mut preview: Query<(Entity, &mut Transform, &mut Visibility), With<PastePreview>>,
+) {
+    for (entity, mut transform, mut visibility) in &mut preview {
+        if clipboard.is_changed() {
+            commands.entity(entity).despawn_descendants();
+
+            if let Some(copied) = &clipboard.copied {
+                commands.entity(entity).with_children(|parent| {
+                    for (position, _) in copied.at(BlockPosition { x: 0, y: 0, z: 0 }) {
+                        parent.spawn_bundle(PbrBundle {
+                            mesh: assets.cube.clone(),
+                            material: material.0.clone(),
+                            transform: position.into_transform(),
+                            ..default()
+                        });
+                    }
+                });
+            }
+        }
+
+        match paste_origin(&pointed).filter(|_| *mode == ToolMode::Paste) {
+            Some(origin) => {
+                visibility.is_visible = true;
+                *transform = origin.into_transform();
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
+
+/// Copies the blocks of the selected box, to paste them elsewhere, turned with R if needed.
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>()
+            .add_startup_system(setup_paste_preview)
+            .add_system(copy_selection)
+            .add_system(toggle_paste)
+            .add_system(rotate_paste.after(toggle_paste))
+            .add_system(commit_paste.after(rotate_paste))
+            .add_system(update_paste_preview.after(commit_paste));
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+
+use crate::cli_arg;
+
+/// First bytes of a zstd frame, to tell compressed files from plain text ones.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Fast, while already several times smaller than plain RON.
+const DEFAULT_LEVEL: i32 = 3;
+
+/// Zstd level of the files written, from 1 to 22, set with `--compression-level <level>`.
+///
+/// Level 0 writes them as plain text, e.g. to read or edit a save by hand.
+pub fn level() -> i32 {
+    cli_arg("--compression-level")
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(DEFAULT_LEVEL)
+}
+
+/// Reads a text file, compressed with zstd or not.
+pub fn read_to_string(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = fs::read(path)?;
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        bytes = zstd::decode_all(bytes.as_slice())?;
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Writes a text file, compressed with zstd at `level` unless it is 0.
+///
+/// The content goes to `<path>.tmp` first, then replaces the file, so a crash while writing leaves
+/// the previous file intact.
+pub fn write(path: &str, content: &str, level: i32) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_path = format!("{}.tmp", path);
+
+    if level == 0 {
+        fs::write(&temp_path, content)?;
+    } else {
+        fs::write(&temp_path, zstd::encode_all(content.as_bytes(), level)?)?;
+    }
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockMap, BlockRegistry, BlockType, SelectedBlock};
+use crate::box_select::ToolMode;
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::repeat_placement::face_direction;
+use crate::voxel_raycast::PointedFace;
+use crate::{BlockPosition, FloorTile, MyRaycastSet};
+
+/// Vertical mouse movement, in pixels, needed to extrude by one more cell.
+const PIXELS_PER_CELL: f32 = 40.0;
+/// Maximum number of blocks in an extruded face region.
+const MAX_REGION_SIZE: usize = 4096;
+
+/// A face region being dragged.
+struct Extrusion {
+    region: Vec<BlockPosition>,
+    direction: (i64, i64, i64),
+    /// Type of the pressed block, used for the extruded blocks.
+    block_type: BlockType,
+    drag: f32,
+}
+
+impl Extrusion {
+    /// Number of cells to extrude, negative when dragging inward.
+    fn cells(&self) -> i64 {
+        (self.drag / PIXELS_PER_CELL).round() as i64
+    }
+}
+
+fn offset(position: BlockPosition, (dx, dy, dz): (i64, i64, i64), n: i64) -> BlockPosition {
+    BlockPosition {
+        x: position.x + dx * n,
+        y: position.y + dy * n,
+        z: position.z + dz * n,
+    }
+}
+
+/// Flood fills the blocks sharing the plane of `start` whose face towards `direction` is exposed,
+/// `is_block` telling the cells holding a block.
+fn face_region(
+    start: BlockPosition,
+    direction: (i64, i64, i64),
+    is_block: impl Fn(BlockPosition) -> bool,
+) -> Vec<BlockPosition> {
+    // The two axes spanning the face plane.
+    let steps: [(i64, i64, i64); 4] = match direction {
+        (_, 0, 0) => [(0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)],
+        (0, _, 0) => [(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)],
+        _ => [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0)],
+    };
+    let is_face = |p: BlockPosition| is_block(p) && !is_block(offset(p, direction, 1));
+
+    let _span = info_span!("face_region").entered();
+
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut region = Vec::new();
+
+    while let Some(position) = queue.pop_front() {
+        region.push(position);
+
+        if region.len() >= MAX_REGION_SIZE {
+            warn!(
+                "Extruded face region truncated to {} blocks",
+                MAX_REGION_SIZE
+            );
+            break;
+        }
+
+        for step in steps {
+            let neighbour = offset(position, step, 1);
+            if is_face(neighbour) && visited.insert(neighbour) {
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    region
+}
+
+/// Press on a face to pick its region, drag up to extrude it outward or down to dig it inward.
+fn extrude_face(
+    mut edits: EventWriter<EditBlocks>,
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    map: Res<BlockMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mode: Res<ToolMode>,
+    mut motion: EventReader<MouseMotion>,
+    mut extrusion: Local<Option<Extrusion>>,
+    pointed: Res<PointedFace>,
+    blocks: Query<(), (With<BlockPosition>, Without<FloorTile>)>,
+    types: Query<&BlockType>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    // Floor tiles are left alone.
+    let is_block = |position| {
+        map.get(position)
+            .map_or(false, |entity| blocks.contains(entity))
+    };
+
+    // The other tools use the same clicks.
+    if *mode == ToolMode::Build && input_map.just_pressed(Action::ExtrudeFace, &keys, &mouse) {
+        *extrusion = pointed.0.and_then(|hit| {
+            let entity = map
+                .get(hit.cell)
+                .filter(|&entity| blocks.contains(entity))?;
+            let direction = face_direction(hit.normal);
+
+            Some(Extrusion {
+                region: face_region(hit.cell, direction, &is_block),
+                direction,
+                block_type: types.get(entity).copied().unwrap_or(selected.0),
+                drag: 0.0,
+            })
+        });
+        return;
+    }
+
+    let current = match extrusion.as_mut() {
+        Some(current) => current,
+        None => return,
+    };
+
+    for event in motion.iter() {
+        current.drag -= event.delta.y;
+    }
+
+    if !input_map.just_released(Action::ExtrudeFace, &keys, &mouse) {
+        return;
+    }
+
+    let cells = current.cells();
+    let mut batch = Vec::new();
+
+    if cells > 0 {
+        let direction = current.direction;
+        let mut extruded: Vec<_> = current
+            .region
+            .iter()
+            .flat_map(|&position| (1..=cells).map(move |n| offset(position, direction, n)))
+            .collect();
+        // Cells are placed bottom up, so blocks needing support can rest on the ones extruded
+        // below them.
+        extruded.sort_by_key(|cell| cell.y);
+
+        let mut placed = HashSet::new();
+        let mut rejected = 0;
+        for cell in extruded {
+            let below = BlockPosition {
+                y: cell.y - 1,
+                ..cell
+            };
+            let supported = placed.contains(&below) && cell.in_bounds() && !map.contains(cell);
+
+            if supported || registry.can_place(current.block_type, cell, &map) {
+                placed.insert(cell);
+                batch.push(BlockEdit::Place {
+                    position: cell,
+                    block_type: current.block_type,
+                });
+            } else if !map.contains(cell) {
+                rejected += 1;
+            }
+        }
+
+        if rejected > 0 {
+            let name = registry
+                .get(current.block_type)
+                .map_or("Unknown", |definition| definition.name);
+            warn!(
+                "{} cells not extruded: {} blocks can't be placed there",
+                rejected, name
+            );
+        }
+    } else if cells < 0 {
+        for &position in &current.region {
+            for n in 0..-cells {
+                let cell = offset(position, current.direction, -n);
+                if is_block(cell) {
+                    batch.push(BlockEdit::Remove { position: cell });
+                }
+            }
+        }
+    }
+
+    // A single step in the edit history.
+    if !batch.is_empty() {
+        edits.send(EditBlocks {
+            edits: batch,
+            source: player.get_single().ok(),
+        });
+    }
+    *extrusion = None;
+}
+
+/// Extrudes or digs a whole flat face region at once, Alt+drag by default.
+pub struct ExtrudePlugin;
+
+impl Plugin for ExtrudePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(extrude_face);
+    }
+}
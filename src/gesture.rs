@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+use crate::block::BlockMap;
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{pointed_block, BlockPosition};
+
+/// Maximum delay between the two clicks of a double-click, in seconds.
+const DOUBLE_CLICK_DELAY: f64 = 0.3;
+/// Maximum cursor movement between the two clicks of a double-click, in pixels.
+const DOUBLE_CLICK_DISTANCE: f32 = 4.0;
+
+/// High-level mouse gestures recognized from raw clicks, consumed by the tool systems.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// `Action::Select` clicked twice in quick succession, with the block pointed by the first
+    /// click.
+    DoubleClick(Option<BlockPosition>),
+    /// `Action::AddToSelection` clicked, Shift+click by default.
+    AddClick,
+    /// `Action::RemoveFromSelection` clicked, Ctrl+click by default.
+    RemoveClick,
+}
+
+#[derive(Default)]
+struct LastClick {
+    time: f64,
+    position: Option<Vec2>,
+    target: Option<BlockPosition>,
+}
+
+pub fn recognize_gestures(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    map: Res<BlockMap>,
+    pointed: Res<PointedFace>,
+    mut last_click: Local<LastClick>,
+    mut gestures: EventWriter<Gesture>,
+) {
+    if input_map.just_pressed(Action::AddToSelection, &keys, &mouse) {
+        gestures.send(Gesture::AddClick);
+    }
+
+    if input_map.just_pressed(Action::RemoveFromSelection, &keys, &mouse) {
+        gestures.send(Gesture::RemoveClick);
+    }
+
+    if input_map.just_pressed(Action::Select, &keys, &mouse) {
+        let now = time.seconds_since_startup();
+        let position = windows.get_primary().and_then(|w| w.cursor_position());
+
+        let close = match (last_click.position, position) {
+            (Some(last), Some(current)) => last.distance(current) <= DOUBLE_CLICK_DISTANCE,
+            _ => false,
+        };
+
+        if close && now - last_click.time <= DOUBLE_CLICK_DELAY {
+            gestures.send(Gesture::DoubleClick(last_click.target));
+            // A third click starts a new double-click rather than completing another one.
+            *last_click = LastClick::default();
+        } else {
+            *last_click = LastClick {
+                time: now,
+                position,
+                target: pointed_block(&pointed, &map).map(|(_, position)| position),
+            };
+        }
+    }
+}
+
+/// Turns clicks into `Gesture` events.
+pub struct GesturePlugin;
+
+impl Plugin for GesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Gesture>().add_system(recognize_gestures);
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::view::RenderLayers;
+use bevy::ui::UiCameraConfig;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::BlockMap;
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{pointed_block, BlockPosition, MyRaycastSet};
+
+/// Render layer only seen by the axis gizmo camera.
+const GIZMO_LAYER: u8 = 1;
+/// Size of the axis gizmo in the bottom left corner, in physical pixels.
+const GIZMO_VIEWPORT_SIZE: u32 = 120;
+
+/// Cell the grid is measured from, shown by a marker in the world. Set with `Action::SetOrigin`.
+///
+/// The coordinates of the pointed cell are shown relative to it.
+pub struct GridOrigin(pub BlockPosition);
+
+impl Default for GridOrigin {
+    fn default() -> Self {
+        GridOrigin(BlockPosition { x: 0, y: 0, z: 0 })
+    }
+}
+
+#[derive(Component)]
+struct AxisGizmoCamera;
+
+#[derive(Component)]
+struct OriginMarker;
+
+#[derive(Component)]
+struct CoordinatesText;
+
+/// Spawns red, green and blue arrows along X, Y and Z as children.
+fn spawn_axes(
+    parent: &mut ChildBuilder,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    length: f32,
+    thickness: f32,
+    layers: RenderLayers,
+) {
+    let axes = [
+        (Vec3::X, Color::RED),
+        (Vec3::Y, Color::GREEN),
+        (Vec3::Z, Color::BLUE),
+    ];
+
+    for (axis, color) in axes {
+        let size = axis * length + (Vec3::ONE - axis) * thickness;
+
+        parent
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(size.x, size.y, size.z))),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_translation(axis * length / 2.0),
+                ..default()
+            })
+            .insert(layers);
+    }
+}
+
+fn setup_gizmos(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let gizmo_layers = RenderLayers::layer(GIZMO_LAYER);
+
+    // Drawn over the main view, in its own viewport and render layer.
+    commands
+        .spawn_bundle(Camera3dBundle {
+            camera: Camera {
+                priority: 1,
+                ..default()
+            },
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(gizmo_layers)
+        .insert(AxisGizmoCamera);
+
+    commands
+        .spawn_bundle(SpatialBundle::default())
+        .with_children(|parent| {
+            spawn_axes(parent, &mut meshes, &mut materials, 1.0, 0.08, gizmo_layers)
+        });
+
+    commands
+        .spawn_bundle(SpatialBundle::default())
+        .insert(OriginMarker)
+        .with_children(|parent| {
+            spawn_axes(
+                parent,
+                &mut meshes,
+                &mut materials,
+                1.5,
+                0.04,
+                RenderLayers::default(),
+            )
+        });
+}
+
+/// Keeps the gizmo in the corner of the window, seen from the same angle as the main camera.
+fn update_axis_gizmo_camera(
+    windows: Res<Windows>,
+    main_camera: Query<&Transform, (With<RayCastSource<MyRaycastSet>>, Without<AxisGizmoCamera>)>,
+    mut gizmo_camera: Query<(&mut Transform, &mut Camera), With<AxisGizmoCamera>>,
+) {
+    let (window, main_transform) = match (windows.get_primary(), main_camera.get_single()) {
+        (Some(window), Ok(transform)) => (window, transform),
+        _ => return,
+    };
+
+    let size = GIZMO_VIEWPORT_SIZE
+        .min(window.physical_width())
+        .min(window.physical_height());
+    let position = UVec2::new(0, window.physical_height() - size);
+
+    for (mut transform, mut camera) in &mut gizmo_camera {
+        *transform = Transform::from_translation(main_transform.rotation * Vec3::Z * 3.0)
+            .with_rotation(main_transform.rotation);
+
+        // Only write on resize so the camera isn't flagged as changed every frame.
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|v| (v.physical_position, v.physical_size));
+        if current != Some((position, UVec2::splat(size))) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: UVec2::splat(size),
+                depth: 0.0..1.0,
+            });
+        }
+    }
+}
+
+/// Moves the grid origin to the block under the cursor.
+fn set_grid_origin(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut origin: ResMut<GridOrigin>,
+    map: Res<BlockMap>,
+    pointed: Res<PointedFace>,
+) {
+    if !input_map.just_pressed(Action::SetOrigin, &keys, &mouse) {
+        return;
+    }
+
+    if let Some((_, position)) = pointed_block(&pointed, &map) {
+        origin.0 = position;
+    }
+}
+
+fn update_origin_marker(
+    origin: Res<GridOrigin>,
+    mut query: Query<&mut Transform, With<OriginMarker>>,
+) {
+    if !origin.is_changed() {
+        return;
+    }
+
+    for mut transform in &mut query {
+        *transform = origin.0.into_transform();
+    }
+}
+
+/// Coordinates of the pointed cell, just above the axis gizmo.
+fn spawn_coordinates_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(GIZMO_VIEWPORT_SIZE as f32 + 8.0),
+                    left: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(CoordinatesText);
+}
+
+/// Shows the pointed cell relative to the grid origin, to measure distances between blocks.
+fn update_coordinates_text(
+    origin: Res<GridOrigin>,
+    pointed: Res<PointedFace>,
+    mut query: Query<&mut Text, With<CoordinatesText>>,
+) {
+    let value = match pointed.0 {
+        Some(hit) => format!(
+            "X {}  Y {}  Z {}",
+            hit.cell.x - origin.0.x,
+            hit.cell.y - origin.0.y,
+            hit.cell.z - origin.0.z
+        ),
+        None => String::new(),
+    };
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Axis gizmo in a corner of the screen, a movable marker for the grid origin and the coordinates
+/// of the pointed cell from it.
+pub struct GizmosPlugin;
+
+impl Plugin for GizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridOrigin>()
+            .add_startup_system(setup_gizmos)
+            .add_startup_system(spawn_coordinates_text)
+            .add_system(update_axis_gizmo_camera)
+            .add_system(set_grid_origin)
+            .add_system(update_origin_marker.after(set_grid_origin))
+            .add_system(update_coordinates_text.after(set_grid_origin));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::input_map::{Action, InputMap};
+use crate::persistence::world_path;
+use crate::repeat_placement::face_direction;
+use crate::voxel_raycast::PointedFace;
+use crate::{block_position_from_hit, WORLD_LIMIT};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideAxis {
+    X,
+    Y,
+    Z,
+}
+
+/// A plane crossing the middle of the layer of cells at `layer` along `axis`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Guide {
+    pub axis: GuideAxis,
+    pub layer: i64,
+}
+
+impl Guide {
+    fn transform(&self) -> Transform {
+        let layer = self.layer as f32;
+        // The plane mesh faces up, so it only needs turning for X and Z.
+        let (translation, rotation) = match self.axis {
+            GuideAxis::X => (
+                Vec3::new(layer, 0.0, 0.0),
+                Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2),
+            ),
+            GuideAxis::Y => (Vec3::new(0.0, layer, 0.0), Quat::IDENTITY),
+            GuideAxis::Z => (
+                Vec3::new(0.0, 0.0, layer),
+                Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            ),
+        };
+
+        Transform::from_translation(translation).with_rotation(rotation)
+    }
+}
+
+/// Construction planes used to line up separate structures, kept between sessions.
+#[derive(Serialize, Deserialize)]
+pub struct Guides {
+    pub guides: Vec<Guide>,
+    pub visible: bool,
+}
+
+impl Default for Guides {
+    fn default() -> Self {
+        Guides {
+            guides: Vec::new(),
+            visible: true,
+        }
+    }
+}
+
+/// Guides are saved next to the world they line up, as `<world path>.guides`.
+fn guides_path() -> String {
+    format!("{}.guides", world_path())
+}
+
+impl Guides {
+    fn load() -> Self {
+        fs::read_to_string(guides_path())
+            .ok()
+            .and_then(|content| ron::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|content| fs::write(guides_path(), content).map_err(|err| err.to_string()));
+
+        if let Err(err) = result {
+            error!("Could not save guides: {}", err);
+        }
+    }
+}
+
+struct GuideAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+#[derive(Component)]
+struct GuidePlane;
+
+fn setup_guides(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(GuideAssets {
+        // Covers the whole buildable area.
+        mesh: meshes.add(Mesh::from(shape::Plane {
+            size: (WORLD_LIMIT * 2 + 1) as f32,
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 0.6, 0.0, 0.15),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        }),
+    });
+}
+
+/// Adds a guide on the layer a block would be placed in, or removes it if it is already there.
+fn toggle_guide(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut guides: ResMut<Guides>,
+    pointed: Res<PointedFace>,
+) {
+    if input_map.just_pressed(Action::ToggleGuidesVisible, &keys, &mouse) {
+        guides.visible = !guides.visible;
+        guides.save();
+    }
+
+    if !input_map.just_pressed(Action::PlaceGuide, &keys, &mouse) {
+        return;
+    }
+
+    let hit = match pointed.0 {
+        Some(hit) => hit,
+        None => return,
+    };
+
+    let cell = block_position_from_hit(hit.position, hit.normal);
+    let guide = match face_direction(hit.normal) {
+        (_, 0, 0) => Guide {
+            axis: GuideAxis::X,
+            layer: cell.x,
+        },
+        (0, _, 0) => Guide {
+            axis: GuideAxis::Y,
+            layer: cell.y,
+        },
+        _ => Guide {
+            axis: GuideAxis::Z,
+            layer: cell.z,
+        },
+    };
+
+    match guides.guides.iter().position(|g| *g == guide) {
+        Some(i) => {
+            guides.guides.remove(i);
+        }
+        None => guides.guides.push(guide),
+    }
+    guides.visible = true;
+    guides.save();
+}
+
+fn spawn_guide_planes(
+    mut commands: Commands,
+    guides: Res<Guides>,
+    assets: Res<GuideAssets>,
+    planes: Query<Entity, With<GuidePlane>>,
+) {
+    if !guides.is_changed() {
+        return;
+    }
+
+    for entity in &planes {
+        commands.entity(entity).despawn();
+    }
+
+    if !guides.visible {
+        return;
+    }
+
+    for guide in &guides.guides {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                transform: guide.transform(),
+                ..default()
+            })
+            .insert(GuidePlane);
+    }
+}
+
+/// Guide planes placed on block layers, to align structures built apart from each other.
+pub struct GuidesPlugin;
+
+impl Plugin for GuidesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Guides::load())
+            .add_startup_system(setup_guides)
+            .add_system(toggle_guide)
+            .add_system(spawn_guide_planes.after(toggle_guide));
+    }
+}
@@ -0,0 +1,391 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::input_map::{Action, Binding, Button, InputMap, Modifier, Section};
+use crate::version::version;
+
+/// Lines per column of the overlay, and columns per page, so that it fits a 720p window.
+const ROWS_PER_COLUMN: usize = 18;
+const COLUMNS_PER_PAGE: usize = 2;
+
+#[derive(Component)]
+struct HelpOverlay;
+
+/// Clicking it starts capturing a new binding for the action.
+#[derive(Component)]
+struct RebindButton(Action);
+
+/// Clicking it shows the previous or next page of the overlay.
+#[derive(Component, Clone, Copy)]
+enum PageButton {
+    Previous,
+    Next,
+}
+
+/// The action waiting for its new binding, if any.
+#[derive(Default)]
+struct Rebinding {
+    action: Option<Action>,
+    /// Set one frame after the rebind button was clicked, so that click isn't captured.
+    armed: bool,
+}
+
+/// Page of the overlay shown.
+#[derive(Default)]
+struct HelpPage(usize);
+
+enum HelpLine {
+    Title(Section),
+    Binding(Action, String),
+    Text(&'static str),
+}
+
+/// Every line of the overlay, sections one after the other.
+fn help_lines(input_map: &InputMap, rebinding: &Rebinding) -> Vec<HelpLine> {
+    let mut lines = Vec::new();
+
+    for section in Section::ALL {
+        lines.push(HelpLine::Title(section));
+
+        for (action, binding) in input_map.bindings() {
+            if action.section() != section {
+                continue;
+            }
+
+            let binding = if rebinding.action == Some(action) {
+                "press a key or mouse button...".to_string()
+            } else {
+                binding.to_string()
+            };
+            lines.push(HelpLine::Binding(action, binding));
+        }
+
+        // The scroll wheel is not an action that can be bound.
+        if section == Section::Camera {
+            lines.push(HelpLine::Text("  Zoom: Mouse wheel"));
+        }
+    }
+
+    lines
+}
+
+fn text_style(asset_server: &AssetServer) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/DejaVuSans.ttf"),
+        font_size: 22.0,
+        color: Color::WHITE,
+    }
+}
+
+/// Node stacking its children from the top, UI being laid out from the bottom.
+fn column(margin: UiRect<Val>) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::FlexStart,
+            margin,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    }
+}
+
+fn row() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            // Tops of the columns aligned, UI being laid out from the bottom.
+            align_items: AlignItems::FlexEnd,
+            ..default()
+        },
+        color: Color::NONE.into(),
+        ..default()
+    }
+}
+
+fn spawn_page_button(parent: &mut ChildBuilder, button: PageButton, style: &TextStyle) {
+    let label = match button {
+        PageButton::Previous => "< Previous",
+        PageButton::Next => "Next >",
+    };
+
+    parent
+        .spawn_bundle(ButtonBundle {
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(label, style.clone()));
+        });
+}
+
+/// Builds the overlay from the current bindings, so it never gets out of date. The bindings are
+/// split over columns and pages, since they don't fit on screen at once.
+fn spawn_help_overlay(
+    commands: &mut Commands,
+    input_map: &InputMap,
+    rebinding: &Rebinding,
+    page: usize,
+    asset_server: &AssetServer,
+) {
+    let style = text_style(asset_server);
+    let title_style = TextStyle {
+        font_size: 28.0,
+        ..style.clone()
+    };
+
+    let lines = help_lines(input_map, rebinding);
+    let rows_per_page = ROWS_PER_COLUMN * COLUMNS_PER_PAGE;
+    let pages = (lines.len() + rows_per_page - 1) / rows_per_page;
+    let page = page.min(pages - 1);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..default()
+        })
+        .insert(HelpOverlay)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(column(UiRect::default()))
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section("Controls", title_style.clone()));
+
+                    parent.spawn_bundle(row()).with_children(|parent| {
+                        let page_lines = lines.chunks(rows_per_page).nth(page).unwrap_or(&[]);
+
+                        for column_lines in page_lines.chunks(ROWS_PER_COLUMN) {
+                            let margin = UiRect {
+                                right: Val::Px(32.0),
+                                ..default()
+                            };
+
+                            parent.spawn_bundle(column(margin)).with_children(|parent| {
+                                for line in column_lines {
+                                    match line {
+                                        HelpLine::Title(section) => {
+                                            parent.spawn_bundle(
+                                                TextBundle::from_section(
+                                                    section.title(),
+                                                    title_style.clone(),
+                                                )
+                                                .with_style(Style {
+                                                    margin: UiRect {
+                                                        top: Val::Px(12.0),
+                                                        ..default()
+                                                    },
+                                                    ..default()
+                                                }),
+                                            );
+                                        }
+                                        HelpLine::Binding(action, binding) => {
+                                            parent
+                                                .spawn_bundle(ButtonBundle {
+                                                    color: Color::NONE.into(),
+                                                    ..default()
+                                                })
+                                                .insert(RebindButton(*action))
+                                                .with_children(|parent| {
+                                                    parent.spawn_bundle(TextBundle::from_section(
+                                                        format!(
+                                                            "  {}: {}",
+                                                            action.description(),
+                                                            binding
+                                                        ),
+                                                        style.clone(),
+                                                    ));
+                                                });
+                                        }
+                                        HelpLine::Text(text) => {
+                                            parent.spawn_bundle(TextBundle::from_section(
+                                                *text,
+                                                style.clone(),
+                                            ));
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    });
+
+                    parent.spawn_bundle(
+                        TextBundle::from_section(
+                            "Click a binding to change it, Escape to cancel",
+                            style.clone(),
+                        )
+                        .with_style(Style {
+                            margin: UiRect {
+                                top: Val::Px(12.0),
+                                ..default()
+                            },
+                            ..default()
+                        }),
+                    );
+
+                    parent.spawn_bundle(row()).with_children(|parent| {
+                        if page > 0 {
+                            spawn_page_button(parent, PageButton::Previous, &style);
+                        }
+                        parent.spawn_bundle(TextBundle::from_section(
+                            format!("  Page {}/{}  ", page + 1, pages),
+                            style.clone(),
+                        ));
+                        if page + 1 < pages {
+                            spawn_page_button(parent, PageButton::Next, &style);
+                        }
+                    });
+
+                    parent.spawn_bundle(TextBundle::from_section(
+                        format!("Version {}", version()),
+                        style.clone(),
+                    ));
+                });
+        });
+}
+
+/// Suspends the input map while the overlay is shown, so clicking its buttons doesn't edit the
+/// world behind it. Runs before the tools, and reads its own binding directly to close the overlay.
+fn toggle_help(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut input_map: ResMut<InputMap>,
+    rebinding: Res<Rebinding>,
+    page: Res<HelpPage>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<HelpOverlay>>,
+) {
+    if rebinding.action.is_some() {
+        return;
+    }
+
+    if query.is_empty() {
+        if input_map.just_pressed(Action::ToggleHelp, &keys, &mouse) {
+            spawn_help_overlay(&mut commands, &input_map, &rebinding, page.0, &asset_server);
+            input_map.suspended = true;
+        }
+    } else if input_map
+        .binding(Action::ToggleHelp)
+        .map_or(false, |binding| binding.just_pressed(&keys, &mouse))
+    {
+        for entity in &query {
+            commands.entity(entity).despawn_recursive();
+        }
+        input_map.suspended = false;
+    }
+}
+
+fn turn_help_page(
+    mut page: ResMut<HelpPage>,
+    query: Query<(&Interaction, &PageButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &query {
+        if *interaction == Interaction::Clicked {
+            match button {
+                PageButton::Previous => page.0 = page.0.saturating_sub(1),
+                PageButton::Next => page.0 += 1,
+            }
+        }
+    }
+}
+
+fn start_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    query: Query<(&Interaction, &RebindButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &query {
+        if *interaction == Interaction::Clicked {
+            *rebinding = Rebinding {
+                action: Some(button.0),
+                armed: false,
+            };
+        }
+    }
+}
+
+/// Binds the next pressed key or mouse button, along with the held modifier, to the action.
+///
+/// The input map stays suspended by the overlay, so the captured press doesn't also trigger the
+/// action it is currently bound to.
+fn capture_rebinding(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut input_map: ResMut<InputMap>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let action = match rebinding.action {
+        Some(action) => action,
+        None => return,
+    };
+
+    if !rebinding.armed {
+        rebinding.armed = true;
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        *rebinding = Rebinding::default();
+        return;
+    }
+
+    let button = keys
+        .get_just_pressed()
+        .find(|key| !Modifier::is_modifier_key(**key))
+        .map(|key| Button::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|b| Button::Mouse(*b)));
+
+    if let Some(button) = button {
+        input_map.bind(
+            action,
+            Binding {
+                modifier: Modifier::held(&keys),
+                button,
+            },
+        );
+        *rebinding = Rebinding::default();
+    }
+}
+
+/// Rebuilds the overlay when a binding is being captured or has changed, or the page turned.
+fn refresh_help(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    rebinding: Res<Rebinding>,
+    page: Res<HelpPage>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<HelpOverlay>>,
+) {
+    if query.is_empty() || !(input_map.is_changed() || rebinding.is_changed() || page.is_changed())
+    {
+        return;
+    }
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_help_overlay(&mut commands, &input_map, &rebinding, page.0, &asset_server);
+}
+
+/// Help overlay listing the controls by pages, toggled with F1. Bindings can be changed from it.
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .init_resource::<HelpPage>()
+            .add_system_to_stage(CoreStage::PreUpdate, toggle_help.after(InputSystem))
+            .add_system(turn_help_page)
+            .add_system(start_rebinding)
+            .add_system(capture_rebinding.after(start_rebinding))
+            .add_system(refresh_help.after(capture_rebinding).after(turn_help_page));
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::block::{BlockAssets, BlockMap, BlockRegistry, BlockType};
+use crate::chunk::Chunks;
+use crate::input_map::{Action, InputMap};
+use crate::{spawn_cube, spawn_floor_tile, BlockPosition};
+
+/// Maximum number of edits that can be undone.
+const MAX_HISTORY: usize = 100;
+/// Block edits applied per frame. Larger steps, like filling a box, are spread over several frames.
+const MAX_EDITS_PER_FRAME: usize = 1000;
+
+#[derive(Clone, Copy)]
+pub enum BlockEdit {
+    Place {
+        position: BlockPosition,
+        block_type: BlockType,
+    },
+    Remove {
+        position: BlockPosition,
+    },
+    /// Puts back a removed floor tile, when undoing its removal.
+    PlaceFloor {
+        position: BlockPosition,
+    },
+}
+
+/// Event applying edits as a single step of the history, so that e.g. an extrusion is undone at
+/// once. Every tool changing blocks goes through it.
+pub struct EditBlocks {
+    pub edits: Vec<BlockEdit>,
+    /// Entity making the edits, the player's camera for the tools. None for edits like imports.
+    pub source: Option<Entity>,
+}
+
+/// Sent for each block placed by `EditBlocks`, undo and redo included, but not for loaded or
+/// generated blocks, nor for floor tiles put back.
+pub struct BlockPlaced {
+    pub position: BlockPosition,
+    pub block_type: BlockType,
+    /// Spawned with the block, its commands are applied by the time the event is read.
+    pub block: Entity,
+    /// Source of the `EditBlocks`, None when undoing or redoing.
+    pub source: Option<Entity>,
+}
+
+/// Sent for each block removed by `EditBlocks`, undo and redo included.
+pub struct BlockRemoved {
+    pub position: BlockPosition,
+    /// None for blocks without type, like floor tiles.
+    pub block_type: Option<BlockType>,
+    /// Already despawned by the time the event is read.
+    pub block: Entity,
+    /// Source of the `EditBlocks`, None when undoing or redoing.
+    pub source: Option<Entity>,
+}
+
+/// Sends the events of the blocks changed by `apply_edits`.
+#[derive(SystemParam)]
+struct BlockEvents<'w, 's> {
+    placed: EventWriter<'w, 's, BlockPlaced>,
+    removed: EventWriter<'w, 's, BlockRemoved>,
+}
+
+/// Where the inverse of a step goes once it is fully applied.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StepKind {
+    Edit,
+    Undo,
+    Redo,
+}
+
+/// A step being applied over several frames.
+struct PendingStep {
+    kind: StepKind,
+    edits: Vec<BlockEdit>,
+    source: Option<Entity>,
+    /// Number of edits already applied.
+    applied: usize,
+    inverse: Vec<BlockEdit>,
+}
+
+impl PendingStep {
+    fn new(kind: StepKind, edits: Vec<BlockEdit>, source: Option<Entity>) -> Self {
+        PendingStep {
+            kind,
+            edits,
+            source,
+            applied: 0,
+            inverse: Vec::new(),
+        }
+    }
+}
+
+/// Steps to undo and redo, each stored as the edits reverting it.
+#[derive(Default)]
+pub struct EditHistory {
+    undo: Vec<Vec<BlockEdit>>,
+    redo: Vec<Vec<BlockEdit>>,
+    /// Steps waiting to be applied, in order.
+    pending: VecDeque<PendingStep>,
+}
+
+/// Applies the edits and returns the ones reverting them, latest first.
+///
+/// Placing in an occupied cell or removing from an empty one does nothing. Removing a block without
+/// type is taken as removing a floor tile, and reverted by putting one back.
+///
+/// The map is updated right away, since spawns and despawns are deferred.
+fn apply_edits(
+    commands: &mut Commands,
+    registry: &BlockRegistry,
+    assets: &BlockAssets,
+    map: &mut BlockMap,
+    chunks: &mut Chunks,
+    events: &mut BlockEvents,
+    edits: &[BlockEdit],
+    source: Option<Entity>,
+) -> Vec<BlockEdit> {
+    let mut inverse = Vec::new();
+
+    for &edit in edits {
+        match edit {
+            BlockEdit::Place {
+                position,
+                block_type,
+            } => {
+                if map.contains(position) {
+                    continue;
+                }
+                if let Some(block) = spawn_cube(commands, registry, chunks, position, block_type) {
+                    map.insert(position, block);
+                    inverse.push(BlockEdit::Remove { position });
+                    events.placed.send(BlockPlaced {
+                        position,
+                        block_type,
+                        block,
+                        source,
+                    });
+                }
+            }
+            BlockEdit::Remove { position } => {
+                if let Some(block) = map.remove(position) {
+                    commands.entity(block).despawn_recursive();
+                    let block_type = chunks.get(position);
+                    match block_type {
+                        Some(block_type) => {
+                            chunks.set(position, None);
+                            inverse.push(BlockEdit::Place {
+                                position,
+                                block_type,
+                            });
+                        }
+                        None => inverse.push(BlockEdit::PlaceFloor { position }),
+                    }
+                    events.removed.send(BlockRemoved {
+                        position,
+                        block_type,
+                        block,
+                        source,
+                    });
+                }
+            }
+            BlockEdit::PlaceFloor { position } => {
+                if map.contains(position) {
+                    continue;
+                }
+                let entity = spawn_floor_tile(commands, assets, position);
+                map.insert(position, entity);
+                inverse.push(BlockEdit::Remove { position });
+            }
+        }
+    }
+
+    inverse.reverse();
+    inverse
+}
+
+fn apply_block_edits(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    assets: Res<BlockAssets>,
+    mut history: ResMut<EditHistory>,
+    mut map: ResMut<BlockMap>,
+    mut chunks: ResMut<Chunks>,
+    mut block_events: BlockEvents,
+    mut events: EventReader<EditBlocks>,
+) {
+    let undo = input_map.just_pressed(Action::Undo, &keys, &mouse);
+    let redo = input_map.just_pressed(Action::Redo, &keys, &mouse);
+    let events: Vec<&EditBlocks> = events.iter().collect();
+
+    if events.is_empty() && !undo && !redo && history.pending.is_empty() {
+        return;
+    }
+
+    let _span = info_span!("apply_block_edits").entered();
+
+    for event in events {
+        history.pending.push_back(PendingStep::new(
+            StepKind::Edit,
+            event.edits.clone(),
+            event.source,
+        ));
+    }
+
+    // Undoing while a step is still being applied would revert the wrong one.
+    if history.pending.is_empty() {
+        if undo {
+            if let Some(step) = history.undo.pop() {
+                history
+                    .pending
+                    .push_back(PendingStep::new(StepKind::Undo, step, None));
+            }
+        } else if redo {
+            if let Some(step) = history.redo.pop() {
+                history
+                    .pending
+                    .push_back(PendingStep::new(StepKind::Redo, step, None));
+            }
+        }
+    }
+
+    let mut budget = MAX_EDITS_PER_FRAME;
+    while budget > 0 {
+        let step = match history.pending.front_mut() {
+            Some(step) => step,
+            None => break,
+        };
+
+        let end = (step.applied + budget).min(step.edits.len());
+        let edits = &step.edits[step.applied..end];
+        let mut inverse = apply_edits(
+            &mut commands,
+            &registry,
+            &assets,
+            &mut map,
+            &mut chunks,
+            &mut block_events,
+            edits,
+            step.source,
+        );
+        budget -= edits.len();
+        step.applied = end;
+
+        // Latest first, so the batches applied earlier are reverted last.
+        inverse.append(&mut step.inverse);
+        step.inverse = inverse;
+
+        if step.applied < step.edits.len() {
+            break;
+        }
+
+        let step = history.pending.pop_front().unwrap();
+        match step.kind {
+            StepKind::Edit => {
+                if !step.inverse.is_empty() {
+                    history.undo.push(step.inverse);
+                    history.redo.clear();
+                }
+            }
+            StepKind::Undo => history.redo.push(step.inverse),
+            StepKind::Redo => history.undo.push(step.inverse),
+        }
+    }
+
+    if history.undo.len() > MAX_HISTORY {
+        let excess = history.undo.len() - MAX_HISTORY;
+        history.undo.drain(..excess);
+    }
+}
+
+fn log_block_events(mut placed: EventReader<BlockPlaced>, mut removed: EventReader<BlockRemoved>) {
+    for event in placed.iter() {
+        debug!(
+            "Placed {:?} at {:?} ({:?}, by {:?})",
+            event.block_type, event.position, event.block, event.source
+        );
+    }
+    for event in removed.iter() {
+        debug!(
+            "Removed {:?} at {:?} ({:?}, by {:?})",
+            event.block_type, event.position, event.block, event.source
+        );
+    }
+}
+
+/// Single path for block edits, with undo (Ctrl+Z) and redo (Ctrl+Y). Features reacting to edits,
+/// like sounds or effects, read `BlockPlaced` and `BlockRemoved`.
+///
+/// At most `MAX_EDITS_PER_FRAME` edits are applied per frame, so large steps don't stall a frame.
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistory>()
+            .add_event::<EditBlocks>()
+            .add_event::<BlockPlaced>()
+            .add_event::<BlockRemoved>()
+            .add_system(log_block_events)
+            // Applied at the start of the next frame, so new blocks get their transform propagated
+            // before being drawn.
+            .add_system_to_stage(CoreStage::PreUpdate, apply_block_edits.after(InputSystem));
+    }
+}
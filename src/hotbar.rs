@@ -0,0 +1,185 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+use crate::block::{BlockRegistry, BlockType, SelectedBlock};
+use crate::input_map::{Action, InputMap};
+
+/// Actions selecting each slot, in order.
+const SLOT_ACTIONS: [Action; 9] = [
+    Action::SelectSlot1,
+    Action::SelectSlot2,
+    Action::SelectSlot3,
+    Action::SelectSlot4,
+    Action::SelectSlot5,
+    Action::SelectSlot6,
+    Action::SelectSlot7,
+    Action::SelectSlot8,
+    Action::SelectSlot9,
+];
+
+const SLOT_SIZE: f32 = 48.0;
+const FRAME_COLOR: Color = Color::rgba(0.1, 0.1, 0.1, 0.8);
+const SELECTED_FRAME_COLOR: Color = Color::WHITE;
+
+/// Frame of a slot, highlighted when its block type is selected.
+#[derive(Component)]
+struct HotbarSlot(BlockType);
+
+/// Name of the selected block type, above the hotbar.
+#[derive(Component)]
+struct HotbarLabel;
+
+fn setup_hotbar(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    materials: Res<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/DejaVuSans.ttf"),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(8.0),
+                    ..default()
+                },
+                // UI is laid out from the bottom, so reverse to put the label on top.
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(HotbarLabel);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (index, block_type) in registry.types().take(SLOT_ACTIONS.len()).enumerate()
+                    {
+                        let color = registry
+                            .get(block_type)
+                            .and_then(|definition| materials.get(&definition.material))
+                            .map_or(Color::GRAY, |material| material.base_color);
+
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(SLOT_SIZE), Val::Px(SLOT_SIZE)),
+                                    padding: UiRect::all(Val::Px(3.0)),
+                                    margin: UiRect::all(Val::Px(2.0)),
+                                    ..default()
+                                },
+                                color: FRAME_COLOR.into(),
+                                ..default()
+                            })
+                            .insert(HotbarSlot(block_type))
+                            .with_children(|parent| {
+                                parent
+                                    .spawn_bundle(NodeBundle {
+                                        style: Style {
+                                            size: Size::new(
+                                                Val::Percent(100.0),
+                                                Val::Percent(100.0),
+                                            ),
+                                            padding: UiRect::all(Val::Px(2.0)),
+                                            ..default()
+                                        },
+                                        color: color.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn_bundle(TextBundle::from_section(
+                                            (index + 1).to_string(),
+                                            style.clone(),
+                                        ));
+                                    });
+                            });
+                    }
+                });
+        });
+}
+
+/// Selects a slot with its number key, or the next and previous ones by scrolling with
+/// `Action::CycleHotbar` held.
+fn select_hotbar_slot(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    mut selected: ResMut<SelectedBlock>,
+    mut scroll: EventReader<MouseWheel>,
+) {
+    let slots: Vec<BlockType> = registry.types().take(SLOT_ACTIONS.len()).collect();
+
+    for (action, block_type) in SLOT_ACTIONS.iter().zip(&slots) {
+        if input_map.just_pressed(*action, &keys, &mouse) {
+            selected.0 = *block_type;
+        }
+    }
+
+    let scrolled: f32 = scroll.iter().map(|event| event.y).sum();
+    if scrolled == 0.0 || !input_map.pressed(Action::CycleHotbar, &keys, &mouse) {
+        return;
+    }
+
+    if let Some(current) = slots
+        .iter()
+        .position(|block_type| *block_type == selected.0)
+    {
+        // Scrolling up goes back to the previous slot.
+        let step = if scrolled > 0.0 { slots.len() - 1 } else { 1 };
+        selected.0 = slots[(current + step) % slots.len()];
+    }
+}
+
+fn update_hotbar(
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    mut slots: Query<(&HotbarSlot, &mut UiColor)>,
+    mut label: Query<&mut Text, With<HotbarLabel>>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    for (slot, mut color) in &mut slots {
+        *color = if slot.0 == selected.0 {
+            SELECTED_FRAME_COLOR.into()
+        } else {
+            FRAME_COLOR.into()
+        };
+    }
+
+    let name = registry
+        .get(selected.0)
+        .map_or("", |definition| definition.name);
+    for mut text in &mut label {
+        text.sections[0].value = name.to_string();
+    }
+}
+
+/// Bar of block types at the bottom of the screen, selecting the type placed by the tools.
+pub struct HotbarPlugin;
+
+impl Plugin for HotbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_hotbar)
+            .add_system(select_hotbar_slot)
+            .add_system(update_hotbar.after(select_hotbar_slot));
+    }
+}
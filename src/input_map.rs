@@ -0,0 +1,658 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+/// A modifier key that must be held for a binding to trigger. Left and right keys are equivalent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 3] = [Modifier::Shift, Modifier::Ctrl, Modifier::Alt];
+
+    fn keys(&self) -> [KeyCode; 2] {
+        match self {
+            Modifier::Shift => [KeyCode::LShift, KeyCode::RShift],
+            Modifier::Ctrl => [KeyCode::LControl, KeyCode::RControl],
+            Modifier::Alt => [KeyCode::LAlt, KeyCode::RAlt],
+        }
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>) -> bool {
+        keys.any_pressed(self.keys())
+    }
+
+    /// The first modifier currently held, if any.
+    pub fn held(keys: &Input<KeyCode>) -> Option<Modifier> {
+        Modifier::ALL.into_iter().find(|m| m.pressed(keys))
+    }
+
+    pub fn is_modifier_key(key: KeyCode) -> bool {
+        Modifier::ALL.iter().any(|m| m.keys().contains(&key))
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Shift => write!(f, "Shift"),
+            Modifier::Ctrl => write!(f, "Ctrl"),
+            Modifier::Alt => write!(f, "Alt"),
+        }
+    }
+}
+
+/// A physical key or mouse button.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Button {
+    fn pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match *self {
+            Button::Key(key) => keys.pressed(key),
+            Button::Mouse(button) => mouse.pressed(button),
+        }
+    }
+
+    fn just_pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match *self {
+            Button::Key(key) => keys.just_pressed(key),
+            Button::Mouse(button) => mouse.just_pressed(button),
+        }
+    }
+
+    fn just_released(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match *self {
+            Button::Key(key) => keys.just_released(key),
+            Button::Mouse(button) => mouse.just_released(button),
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Button::Key(key) => write!(f, "{:?}", key),
+            Button::Mouse(button) => write!(f, "{:?} mouse button", button),
+        }
+    }
+}
+
+/// A button, optionally combined with a modifier, that an action can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub modifier: Option<Modifier>,
+    pub button: Button,
+}
+
+impl Binding {
+    pub fn key(key: KeyCode) -> Self {
+        Binding {
+            modifier: None,
+            button: Button::Key(key),
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Binding {
+            modifier: None,
+            button: Button::Mouse(button),
+        }
+    }
+
+    /// Whether the binding was just pressed, regardless of the input map being suspended.
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        self.modifier
+            .map_or(true, |modifier| modifier.pressed(keys))
+            && self.button.just_pressed(keys, mouse)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.modifier {
+            Some(modifier) => write!(f, "{}+{}", modifier, self.button),
+            None => write!(f, "{}", self.button),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Camera,
+    Tools,
+    General,
+}
+
+impl Section {
+    pub const ALL: [Section; 3] = [Section::Camera, Section::Tools, Section::General];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Section::Camera => "Camera",
+            Section::Tools => "Tools",
+            Section::General => "General",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Orbit,
+    Pan,
+    ReturnToSpawn,
+    ToggleFlyCamera,
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    FlyUp,
+    FlyDown,
+    PlaceBlock,
+    CycleBlockType,
+    SelectSlot1,
+    SelectSlot2,
+    SelectSlot3,
+    SelectSlot4,
+    SelectSlot5,
+    SelectSlot6,
+    SelectSlot7,
+    SelectSlot8,
+    SelectSlot9,
+    CycleHotbar,
+    RemoveBlock,
+    RemoveFloorBlock,
+    PlaceMapTable,
+    Select,
+    AddToSelection,
+    RemoveFromSelection,
+    ToggleRepeatPlacement,
+    ExtrudeFace,
+    SetOrigin,
+    SetSpawn,
+    PlaceGuide,
+    ToggleGuidesVisible,
+    NameRegion,
+    ToggleLegend,
+    ToggleProfiler,
+    ToggleAnalytics,
+    ToggleLowPower,
+    ToggleBlueprint,
+    PlaceReference,
+    GrowReference,
+    ShrinkReference,
+    LockReference,
+    ToggleBoxSelect,
+    SetBoxCorner,
+    FillBox,
+    ClearBox,
+    ReplaceBox,
+    Copy,
+    Paste,
+    RotatePaste,
+    CommitPaste,
+    ToggleBucket,
+    BucketFill,
+    BucketFillVolume,
+    Undo,
+    Redo,
+    SaveWorld,
+    ExportStats,
+    ExportVox,
+    ImportVox,
+    ExportObj,
+    FinishSpeedBuild,
+    ToggleCommandPalette,
+    ToggleHelp,
+}
+
+impl Action {
+    pub fn section(&self) -> Section {
+        match self {
+            Action::Orbit
+            | Action::Pan
+            | Action::ReturnToSpawn
+            | Action::ToggleFlyCamera
+            | Action::MoveForward
+            | Action::MoveBackward
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::FlyUp
+            | Action::FlyDown => Section::Camera,
+            Action::PlaceBlock
+            | Action::CycleBlockType
+            | Action::SelectSlot1
+            | Action::SelectSlot2
+            | Action::SelectSlot3
+            | Action::SelectSlot4
+            | Action::SelectSlot5
+            | Action::SelectSlot6
+            | Action::SelectSlot7
+            | Action::SelectSlot8
+            | Action::SelectSlot9
+            | Action::CycleHotbar
+            | Action::RemoveBlock
+            | Action::RemoveFloorBlock
+            | Action::PlaceMapTable
+            | Action::Select
+            | Action::AddToSelection
+            | Action::RemoveFromSelection
+            | Action::ToggleRepeatPlacement
+            | Action::ExtrudeFace
+            | Action::SetOrigin
+            | Action::SetSpawn
+            | Action::PlaceGuide
+            | Action::ToggleGuidesVisible
+            | Action::NameRegion
+            | Action::ToggleBlueprint
+            | Action::PlaceReference
+            | Action::GrowReference
+            | Action::ShrinkReference
+            | Action::LockReference
+            | Action::ToggleBoxSelect
+            | Action::SetBoxCorner
+            | Action::FillBox
+            | Action::ClearBox
+            | Action::ReplaceBox
+            | Action::Copy
+            | Action::Paste
+            | Action::RotatePaste
+            | Action::CommitPaste
+            | Action::ToggleBucket
+            | Action::BucketFill
+            | Action::BucketFillVolume => Section::Tools,
+            Action::ToggleLegend
+            | Action::ToggleProfiler
+            | Action::ToggleAnalytics
+            | Action::ToggleLowPower
+            | Action::Undo
+            | Action::Redo
+            | Action::SaveWorld
+            | Action::ExportStats
+            | Action::ExportVox
+            | Action::ImportVox
+            | Action::ExportObj
+            | Action::FinishSpeedBuild
+            | Action::ToggleCommandPalette
+            | Action::ToggleHelp => Section::General,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Orbit => "Orbit (hold and drag)",
+            Action::Pan => "Pan (hold and drag)",
+            Action::ReturnToSpawn => "Fly back to spawn point",
+            Action::ToggleFlyCamera => "Switch between orbit and first-person fly camera",
+            Action::MoveForward => "Fly forward",
+            Action::MoveBackward => "Fly backward",
+            Action::MoveLeft => "Fly left",
+            Action::MoveRight => "Fly right",
+            Action::FlyUp => "Fly up",
+            Action::FlyDown => "Fly down",
+            Action::PlaceBlock => "Place block",
+            Action::CycleBlockType => "Next block type",
+            Action::SelectSlot1 => "Select hotbar slot 1",
+            Action::SelectSlot2 => "Select hotbar slot 2",
+            Action::SelectSlot3 => "Select hotbar slot 3",
+            Action::SelectSlot4 => "Select hotbar slot 4",
+            Action::SelectSlot5 => "Select hotbar slot 5",
+            Action::SelectSlot6 => "Select hotbar slot 6",
+            Action::SelectSlot7 => "Select hotbar slot 7",
+            Action::SelectSlot8 => "Select hotbar slot 8",
+            Action::SelectSlot9 => "Select hotbar slot 9",
+            Action::CycleHotbar => "Cycle hotbar slots (hold and scroll)",
+            Action::RemoveBlock => "Remove block (click)",
+            Action::RemoveFloorBlock => "Remove block, floor included (click)",
+            Action::PlaceMapTable => "Place map table",
+            Action::Select => "Select structure (double-click)",
+            Action::AddToSelection => "Add block to selection",
+            Action::RemoveFromSelection => "Remove block from selection",
+            Action::ToggleRepeatPlacement => "Toggle hold-to-repeat placement",
+            Action::ExtrudeFace => "Extrude face (drag up or down)",
+            Action::SetOrigin => "Move grid origin to pointed block",
+            Action::SetSpawn => "Move spawn point to pointed block",
+            Action::PlaceGuide => "Add or remove guide plane on pointed layer",
+            Action::ToggleGuidesVisible => "Show or hide guide planes",
+            Action::NameRegion => "Name the region around the selection",
+            Action::ToggleLegend => "Toggle region legend",
+            Action::ToggleProfiler => "Toggle performance overlay",
+            Action::ToggleAnalytics => "Toggle usage dashboard",
+            Action::ToggleLowPower => "Toggle low power mode",
+            Action::ToggleBlueprint => "Toggle blueprint overlay",
+            Action::PlaceReference => "Move reference image to pointed face",
+            Action::GrowReference => "Grow reference image",
+            Action::ShrinkReference => "Shrink reference image",
+            Action::LockReference => "Lock or unlock reference image",
+            Action::ToggleBoxSelect => "Switch between building and box selection",
+            Action::SetBoxCorner => "Pick box corner (box selection)",
+            Action::FillBox => "Fill box with selected block type",
+            Action::ClearBox => "Clear box",
+            Action::ReplaceBox => "Replace blocks in box with selected type",
+            Action::Copy => "Copy blocks in box",
+            Action::Paste => "Start or stop pasting copied blocks",
+            Action::RotatePaste => "Rotate pasted blocks",
+            Action::CommitPaste => "Paste at cursor (pasting)",
+            Action::ToggleBucket => "Switch between building and paint bucket",
+            Action::BucketFill => "Repaint connected blocks on the clicked face (bucket)",
+            Action::BucketFillVolume => "Repaint all connected blocks (bucket)",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::SaveWorld => "Save world",
+            Action::ExportStats => "Export world statistics",
+            Action::ExportVox => "Export world to MagicaVoxel file",
+            Action::ImportVox => "Import MagicaVoxel file",
+            Action::ExportObj => "Export world to OBJ mesh",
+            Action::FinishSpeedBuild => "Finish speed-build run",
+            Action::ToggleCommandPalette => "Search actions",
+            Action::ToggleHelp => "Toggle this help",
+        }
+    }
+}
+
+/// Maps actions to the inputs triggering them, so every system reads bindings from one place.
+pub struct InputMap {
+    bindings: Vec<(Action, Binding)>,
+    /// Set while typing text or while the help overlay is shown, so inputs don't trigger actions.
+    pub suspended: bool,
+    /// Actions triggered without their binding, reported as just pressed for the next frame.
+    pending: Vec<Action>,
+    triggered: Vec<Action>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            bindings: vec![
+                (Action::Orbit, Binding::mouse(MouseButton::Middle)),
+                (
+                    Action::Pan,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Mouse(MouseButton::Middle),
+                    },
+                ),
+                (Action::ReturnToSpawn, Binding::key(KeyCode::Home)),
+                (Action::ToggleFlyCamera, Binding::key(KeyCode::F)),
+                (Action::MoveForward, Binding::key(KeyCode::W)),
+                (Action::MoveBackward, Binding::key(KeyCode::S)),
+                (Action::MoveLeft, Binding::key(KeyCode::A)),
+                (Action::MoveRight, Binding::key(KeyCode::D)),
+                (Action::FlyUp, Binding::key(KeyCode::Space)),
+                (Action::FlyDown, Binding::key(KeyCode::C)),
+                (Action::PlaceBlock, Binding::mouse(MouseButton::Left)),
+                (Action::CycleBlockType, Binding::key(KeyCode::Tab)),
+                (Action::SelectSlot1, Binding::key(KeyCode::Key1)),
+                (Action::SelectSlot2, Binding::key(KeyCode::Key2)),
+                (Action::SelectSlot3, Binding::key(KeyCode::Key3)),
+                (Action::SelectSlot4, Binding::key(KeyCode::Key4)),
+                (Action::SelectSlot5, Binding::key(KeyCode::Key5)),
+                (Action::SelectSlot6, Binding::key(KeyCode::Key6)),
+                (Action::SelectSlot7, Binding::key(KeyCode::Key7)),
+                (Action::SelectSlot8, Binding::key(KeyCode::Key8)),
+                (Action::SelectSlot9, Binding::key(KeyCode::Key9)),
+                (Action::CycleHotbar, Binding::key(KeyCode::LControl)),
+                (Action::RemoveBlock, Binding::mouse(MouseButton::Right)),
+                (
+                    Action::RemoveFloorBlock,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Mouse(MouseButton::Right),
+                    },
+                ),
+                (Action::PlaceMapTable, Binding::key(KeyCode::M)),
+                // Left clicks place blocks, and a double-click would place two.
+                (Action::Select, Binding::mouse(MouseButton::Middle)),
+                (
+                    Action::AddToSelection,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Mouse(MouseButton::Left),
+                    },
+                ),
+                (
+                    Action::RemoveFromSelection,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Mouse(MouseButton::Left),
+                    },
+                ),
+                (Action::ToggleRepeatPlacement, Binding::key(KeyCode::T)),
+                (
+                    Action::ExtrudeFace,
+                    Binding {
+                        modifier: Some(Modifier::Alt),
+                        button: Button::Mouse(MouseButton::Left),
+                    },
+                ),
+                (Action::SetOrigin, Binding::key(KeyCode::O)),
+                (
+                    Action::SetSpawn,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Key(KeyCode::O),
+                    },
+                ),
+                (Action::PlaceGuide, Binding::key(KeyCode::G)),
+                (Action::ToggleGuidesVisible, Binding::key(KeyCode::H)),
+                (Action::NameRegion, Binding::key(KeyCode::N)),
+                (Action::ToggleLegend, Binding::key(KeyCode::L)),
+                (Action::ToggleBlueprint, Binding::key(KeyCode::B)),
+                (Action::PlaceReference, Binding::key(KeyCode::I)),
+                (Action::GrowReference, Binding::key(KeyCode::RBracket)),
+                (Action::ShrinkReference, Binding::key(KeyCode::LBracket)),
+                (Action::LockReference, Binding::key(KeyCode::K)),
+                (Action::ToggleBoxSelect, Binding::key(KeyCode::V)),
+                (Action::SetBoxCorner, Binding::mouse(MouseButton::Left)),
+                (
+                    Action::FillBox,
+                    Binding {
+                        modifier: Some(Modifier::Alt),
+                        button: Button::Key(KeyCode::F),
+                    },
+                ),
+                (
+                    Action::ClearBox,
+                    Binding {
+                        modifier: Some(Modifier::Alt),
+                        button: Button::Key(KeyCode::C),
+                    },
+                ),
+                (
+                    Action::ReplaceBox,
+                    Binding {
+                        modifier: Some(Modifier::Alt),
+                        button: Button::Key(KeyCode::R),
+                    },
+                ),
+                (
+                    Action::Copy,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::C),
+                    },
+                ),
+                (
+                    Action::Paste,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::V),
+                    },
+                ),
+                (Action::RotatePaste, Binding::key(KeyCode::R)),
+                (Action::CommitPaste, Binding::mouse(MouseButton::Left)),
+                (Action::ToggleBucket, Binding::key(KeyCode::Q)),
+                (Action::BucketFill, Binding::mouse(MouseButton::Left)),
+                (
+                    Action::BucketFillVolume,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Mouse(MouseButton::Left),
+                    },
+                ),
+                (Action::ToggleProfiler, Binding::key(KeyCode::F3)),
+                (Action::ToggleAnalytics, Binding::key(KeyCode::U)),
+                (Action::ToggleLowPower, Binding::key(KeyCode::P)),
+                (
+                    Action::Undo,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::Z),
+                    },
+                ),
+                (
+                    Action::Redo,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::Y),
+                    },
+                ),
+                (
+                    Action::SaveWorld,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::S),
+                    },
+                ),
+                (
+                    Action::ExportStats,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::E),
+                    },
+                ),
+                (
+                    Action::ExportVox,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Key(KeyCode::E),
+                    },
+                ),
+                (
+                    Action::ImportVox,
+                    Binding {
+                        modifier: Some(Modifier::Shift),
+                        button: Button::Key(KeyCode::I),
+                    },
+                ),
+                (
+                    Action::ExportObj,
+                    Binding {
+                        modifier: Some(Modifier::Alt),
+                        button: Button::Key(KeyCode::E),
+                    },
+                ),
+                (Action::FinishSpeedBuild, Binding::key(KeyCode::Return)),
+                (
+                    Action::ToggleCommandPalette,
+                    Binding {
+                        modifier: Some(Modifier::Ctrl),
+                        button: Button::Key(KeyCode::P),
+                    },
+                ),
+                (Action::ToggleHelp, Binding::key(KeyCode::F1)),
+            ],
+            suspended: false,
+            pending: Vec::new(),
+            triggered: Vec::new(),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, Binding)> + '_ {
+        self.bindings.iter().copied()
+    }
+
+    pub fn binding(&self, action: Action) -> Option<Binding> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, binding)| *binding)
+    }
+
+    /// Makes the action just pressed during the next frame, as if its binding was pressed.
+    pub fn trigger(&mut self, action: Action) {
+        self.pending.push(action);
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, current)) => *current = binding,
+            None => self.bindings.push((action, binding)),
+        }
+    }
+
+    /// Returns the binding of the action if its modifier state matches the keyboard.
+    ///
+    /// A binding without modifier is shadowed while the modifier of another binding on the same
+    /// button is held, so that e.g. Left and Alt+Left can be bound to different actions.
+    fn active_binding(&self, action: Action, keys: &Input<KeyCode>) -> Option<Binding> {
+        if self.suspended {
+            return None;
+        }
+
+        let binding = self.binding(action)?;
+
+        match binding.modifier {
+            Some(modifier) => modifier.pressed(keys).then(|| binding),
+            None => {
+                let shadowed = self.bindings.iter().any(|(_, other)| {
+                    other.button == binding.button
+                        && other.modifier.map_or(false, |m| m.pressed(keys))
+                });
+                (!shadowed).then(|| binding)
+            }
+        }
+    }
+
+    pub fn pressed(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+    ) -> bool {
+        self.active_binding(action, keys)
+            .map_or(false, |binding| binding.button.pressed(keys, mouse))
+    }
+
+    pub fn just_pressed(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+    ) -> bool {
+        self.triggered.contains(&action)
+            || self
+                .active_binding(action, keys)
+                .map_or(false, |binding| binding.button.just_pressed(keys, mouse))
+    }
+
+    /// Releasing ignores modifiers, so letting go of the modifier first doesn't leave an action stuck.
+    pub fn just_released(
+        &self,
+        action: Action,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+    ) -> bool {
+        self.binding(action)
+            .map_or(false, |binding| binding.button.just_released(keys, mouse))
+    }
+}
+
+/// Moves the actions triggered during the last frame to this one, so every system sees them.
+pub fn update_triggered_actions(mut input_map: ResMut<InputMap>) {
+    // Only borrowed mutably when needed, so the map isn't flagged as changed every frame.
+    if input_map.pending.is_empty() && input_map.triggered.is_empty() {
+        return;
+    }
+
+    let input_map = &mut *input_map;
+    input_map.triggered = std::mem::take(&mut input_map.pending);
+}
@@ -0,0 +1,194 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::chunk::Chunks;
+use crate::cli_arg;
+use crate::input_map::{Action, InputMap};
+use crate::mesher::{ChunkMesher, ATTRIBUTE_BLOCK_TYPE};
+
+const DEFAULT_OBJ_PATH: &str = "world.obj";
+
+/// File written by `Action::ExportObj`, set with `--obj <path>`. Materials go to the `.mtl` file
+/// next to it.
+struct ObjPath(String);
+
+/// Writes the block types as materials of their color.
+fn write_mtl(
+    registry: &BlockRegistry,
+    materials: &Assets<StandardMaterial>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut mtl = String::new();
+
+    for block_type in registry.types() {
+        let definition = match registry.get(block_type) {
+            Some(definition) => definition,
+            None => continue,
+        };
+        let [r, g, b, a] = materials
+            .get(&definition.material)
+            .map_or([1.0; 4], |material| material.base_color.as_rgba_f32());
+
+        writeln!(mtl, "newmtl {}", definition.name)?;
+        writeln!(mtl, "Kd {} {} {}", r, g, b)?;
+        writeln!(mtl, "d {}", a)?;
+    }
+
+    Ok(mtl)
+}
+
+/// Positions, normals, UVs, block types and triangles of a mesh built by the `ChunkMesher`.
+type MeshData<'a> = (
+    &'a [[f32; 3]],
+    &'a [[f32; 3]],
+    &'a [[f32; 2]],
+    &'a [u32],
+    &'a [u32],
+);
+
+fn mesh_data(mesh: &Mesh) -> Option<MeshData> {
+    match (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)?,
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL)?,
+        mesh.attribute(Mesh::ATTRIBUTE_UV_0)?,
+        mesh.attribute(ATTRIBUTE_BLOCK_TYPE)?,
+        mesh.indices()?,
+    ) {
+        (
+            VertexAttributeValues::Float32x3(positions),
+            VertexAttributeValues::Float32x3(normals),
+            VertexAttributeValues::Float32x2(uvs),
+            VertexAttributeValues::Uint32(block_types),
+            Indices::U32(indices),
+        ) => Some((
+            positions.as_slice(),
+            normals.as_slice(),
+            uvs.as_slice(),
+            block_types.as_slice(),
+            indices.as_slice(),
+        )),
+        _ => None,
+    }
+}
+
+/// Writes the visible faces of every chunk, merged like they are drawn, in a single object with a
+/// material per block type.
+fn write_obj(
+    chunks: &Chunks,
+    registry: &BlockRegistry,
+    mtl_name: &str,
+) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let mesher = ChunkMesher::new(registry, false);
+    let mut obj = String::new();
+    // OBJ indices are global and start at 1.
+    let mut vertices = 1;
+    let mut triangles = 0;
+
+    writeln!(obj, "mtllib {}", mtl_name)?;
+    writeln!(obj, "o world")?;
+
+    for chunk in chunks.positions() {
+        let corner = chunk.corner().into_transform().translation;
+        let meshes = chunks.mesh(&mesher, chunk);
+
+        for mesh in [meshes.opaque, meshes.transparent].into_iter().flatten() {
+            let (positions, normals, uvs, block_types, indices) = match mesh_data(&mesh) {
+                Some(data) => data,
+                None => continue,
+            };
+
+            for [x, y, z] in positions {
+                writeln!(obj, "v {} {} {}", x + corner.x, y + corner.y, z + corner.z)?;
+            }
+            for [u, v] in uvs {
+                writeln!(obj, "vt {} {}", u, v)?;
+            }
+            for [x, y, z] in normals {
+                writeln!(obj, "vn {} {} {}", x, y, z)?;
+            }
+
+            // Block types share the mesh, so the material changes along the triangles.
+            let mut material = None;
+            for triangle in indices.chunks_exact(3) {
+                let block_type = BlockType(block_types[triangle[0] as usize] as u16);
+                if material != Some(block_type) {
+                    let name = registry
+                        .get(block_type)
+                        .map_or("Unknown", |definition| definition.name);
+                    writeln!(obj, "usemtl {}", name)?;
+                    material = Some(block_type);
+                }
+
+                write!(obj, "f")?;
+                for index in triangle {
+                    let index = vertices + *index as usize;
+                    write!(obj, " {0}/{0}/{0}", index)?;
+                }
+                writeln!(obj)?;
+                triangles += 1;
+            }
+
+            vertices += positions.len();
+        }
+    }
+
+    Ok((obj, triangles))
+}
+
+/// Writes the OBJ file at `path` and its materials next to it. Returns the number of triangles.
+fn write_files(
+    path: &str,
+    chunks: &Chunks,
+    registry: &BlockRegistry,
+    materials: &Assets<StandardMaterial>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mtl_path = Path::new(path).with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .ok_or("no file name")?
+        .to_string_lossy()
+        .into_owned();
+    fs::write(&mtl_path, write_mtl(registry, materials)?)?;
+
+    let (obj, triangles) = write_obj(chunks, registry, &mtl_name)?;
+    fs::write(path, obj)?;
+    Ok(triangles)
+}
+
+fn export_obj(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    materials: Res<Assets<StandardMaterial>>,
+    chunks: Res<Chunks>,
+    path: Res<ObjPath>,
+) {
+    if !input_map.just_pressed(Action::ExportObj, &keys, &mouse) {
+        return;
+    }
+
+    let _span = info_span!("export_obj").entered();
+
+    match write_files(&path.0, &chunks, &registry, &materials) {
+        Ok(triangles) => info!("Exported {} triangles to {}", triangles, path.0),
+        Err(err) => error!("Could not export to {}: {}", path.0, err),
+    }
+}
+
+/// Exports the blocks as a single mesh to a Wavefront OBJ file with Alt+E, e.g. to render them in
+/// Blender. Faces hidden by other blocks are left out, and each block type is a material of its
+/// color.
+pub struct ObjPlugin;
+
+impl Plugin for ObjPlugin {
+    fn build(&self, app: &mut App) {
+        let path = cli_arg("--obj").unwrap_or_else(|| DEFAULT_OBJ_PATH.to_string());
+
+        app.insert_resource(ObjPath(path)).add_system(export_obj);
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::block::{BlockRegistry, BlockType, SelectedBlock};
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::{cli_arg, BlockPosition};
+
+const DEFAULT_VOX_PATH: &str = "world.vox";
+/// Version of the format written by MagicaVoxel, and by the export.
+const VOX_VERSION: i32 = 150;
+/// Most cells along each side of a model.
+const MAX_MODEL_SIZE: i64 = 256;
+
+/// First model of a MagicaVoxel `.vox` file, in its own coordinates where Z is up.
+pub struct VoxModel {
+    pub size: (i32, i32, i32),
+    /// Coordinates and color index, from 1 to 255, of each voxel.
+    pub voxels: Vec<(u8, u8, u8, u8)>,
+    /// RGBA color of each index, the first entry being index 1. Files without palette use the
+    /// default one of MagicaVoxel, which isn't known here.
+    pub palette: Option<Vec<[u8; 4]>>,
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> Result<i32, Box<dyn std::error::Error>> {
+    let field = bytes
+        .get(offset..offset + 4)
+        .ok_or("unexpected end of file")?;
+    Ok(i32::from_le_bytes(field.try_into()?))
+}
+
+fn read_len(bytes: &[u8], offset: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    Ok(usize::try_from(read_i32(bytes, offset)?)?)
+}
+
+/// Appends a chunk without children: its id, size and content.
+fn write_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&(content.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(&0i32.to_le_bytes());
+    bytes.extend_from_slice(content);
+}
+
+impl VoxModel {
+    /// Parses a `.vox` file. Only the first model is kept, without the transforms of the scene.
+    pub fn read(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        if !bytes.starts_with(b"VOX ") {
+            return Err("not a MagicaVoxel file".into());
+        }
+        if bytes.get(8..12) != Some(&b"MAIN"[..]) {
+            return Err("missing MAIN chunk".into());
+        }
+
+        let mut size = None;
+        let mut voxels = None;
+        let mut palette = None;
+        let mut models = 0;
+
+        // Every other chunk is a child of MAIN, which has no content of its own.
+        let mut offset = 20 + read_len(bytes, 12)?;
+        while offset < bytes.len() {
+            let id = bytes
+                .get(offset..offset + 4)
+                .ok_or("unexpected end of file")?;
+            let content_size = read_len(bytes, offset + 4)?;
+            let children_size = read_len(bytes, offset + 8)?;
+            let content = bytes
+                .get(offset + 12..offset + 12 + content_size)
+                .ok_or("unexpected end of file")?;
+
+            match id {
+                b"SIZE" if size.is_none() => {
+                    size = Some((
+                        read_i32(content, 0)?,
+                        read_i32(content, 4)?,
+                        read_i32(content, 8)?,
+                    ));
+                }
+                b"XYZI" => {
+                    models += 1;
+                    if voxels.is_none() {
+                        let count = read_len(content, 0)?;
+                        let data = content
+                            .get(4..4 + count * 4)
+                            .ok_or("unexpected end of file")?;
+                        voxels = Some(
+                            data.chunks_exact(4)
+                                .map(|voxel| (voxel[0], voxel[1], voxel[2], voxel[3]))
+                                .collect(),
+                        );
+                    }
+                }
+                b"RGBA" => {
+                    palette = Some(
+                        content
+                            .chunks_exact(4)
+                            .map(|color| [color[0], color[1], color[2], color[3]])
+                            .collect(),
+                    );
+                }
+                _ => {}
+            }
+
+            offset += 12 + content_size + children_size;
+        }
+
+        if models > 1 {
+            warn!("Only the first of the {} models is imported", models);
+        }
+
+        Ok(VoxModel {
+            size: size.ok_or("missing SIZE chunk")?,
+            voxels: voxels.ok_or("missing XYZI chunk")?,
+            palette,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        VoxModel::read(&fs::read(path)?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut size = Vec::new();
+        for side in [self.size.0, self.size.1, self.size.2] {
+            size.extend_from_slice(&side.to_le_bytes());
+        }
+
+        let mut xyzi = (self.voxels.len() as i32).to_le_bytes().to_vec();
+        for &(x, y, z, color) in &self.voxels {
+            xyzi.extend_from_slice(&[x, y, z, color]);
+        }
+
+        // The palette always has 256 entries, the last one being unused.
+        let mut rgba = vec![0; 256 * 4];
+        for (entry, color) in rgba.chunks_exact_mut(4).zip(self.palette.iter().flatten()) {
+            entry.copy_from_slice(color);
+        }
+
+        let mut children = Vec::new();
+        write_chunk(&mut children, b"SIZE", &size);
+        write_chunk(&mut children, b"XYZI", &xyzi);
+        write_chunk(&mut children, b"RGBA", &rgba);
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&VOX_VERSION.to_le_bytes());
+        bytes.extend_from_slice(b"MAIN");
+        bytes.extend_from_slice(&0i32.to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as i32).to_le_bytes());
+        bytes.extend(children);
+        bytes
+    }
+}
+
+/// Color of the material of each block type.
+fn type_colors(
+    registry: &BlockRegistry,
+    materials: &Assets<StandardMaterial>,
+) -> Vec<(BlockType, Color)> {
+    registry
+        .types()
+        .filter_map(|block_type| {
+            let definition = registry.get(block_type)?;
+            let material = materials.get(&definition.material)?;
+            Some((block_type, material.base_color))
+        })
+        .collect()
+}
+
+/// Block type whose color is closest to a palette entry.
+fn nearest_type(colors: &[(BlockType, Color)], [r, g, b, _]: [u8; 4]) -> Option<BlockType> {
+    let distance = |color: &Color| {
+        let [cr, cg, cb, _] = color.as_rgba_f32();
+        (cr - r as f32 / 255.0).powi(2)
+            + (cg - g as f32 / 255.0).powi(2)
+            + (cb - b as f32 / 255.0).powi(2)
+    };
+
+    colors
+        .iter()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(block_type, _)| *block_type)
+}
+
+/// File read and written by `Action::ImportVox` and `Action::ExportVox`, set with `--vox <path>`.
+struct VoxPath(String);
+
+/// Writes every block to a `.vox` model, with a palette entry per block type.
+fn export_vox(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    materials: Res<Assets<StandardMaterial>>,
+    path: Res<VoxPath>,
+    blocks: Query<(&BlockPosition, &BlockType)>,
+) {
+    if !input_map.just_pressed(Action::ExportVox, &keys, &mouse) {
+        return;
+    }
+
+    let _span = info_span!("export_vox").entered();
+
+    let bounds = blocks.iter().fold(None, |bounds, (p, _)| {
+        let (min, max): (BlockPosition, BlockPosition) = bounds.unwrap_or((*p, *p));
+        Some((
+            BlockPosition {
+                x: min.x.min(p.x),
+                y: min.y.min(p.y),
+                z: min.z.min(p.z),
+            },
+            BlockPosition {
+                x: max.x.max(p.x),
+                y: max.y.max(p.y),
+                z: max.z.max(p.z),
+            },
+        ))
+    });
+    let (min, max) = match bounds {
+        Some(bounds) => bounds,
+        None => {
+            warn!("Nothing to export to {}", path.0);
+            return;
+        }
+    };
+
+    let size = (max.x - min.x + 1, max.z - min.z + 1, max.y - min.y + 1);
+    if size.0 > MAX_MODEL_SIZE || size.1 > MAX_MODEL_SIZE || size.2 > MAX_MODEL_SIZE {
+        error!(
+            "Could not export to {}: the world is larger than {} blocks",
+            path.0, MAX_MODEL_SIZE
+        );
+        return;
+    }
+
+    // Color index 0 is empty, so each type is stored at its index plus one.
+    let mut palette = vec![[0; 4]; 255];
+    for (block_type, color) in type_colors(&registry, &materials) {
+        if let Some(entry) = palette.get_mut(block_type.0 as usize) {
+            *entry = color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+        }
+    }
+
+    // MagicaVoxel is Z up, the world's Z axis pointing toward -Y to keep the same handedness.
+    let voxels = blocks
+        .iter()
+        .filter(|(_, block_type)| block_type.0 < 255)
+        .map(|(p, block_type)| {
+            (
+                (p.x - min.x) as u8,
+                (max.z - p.z) as u8,
+                (p.y - min.y) as u8,
+                block_type.0 as u8 + 1,
+            )
+        })
+        .collect();
+
+    let model = VoxModel {
+        size: (size.0 as i32, size.1 as i32, size.2 as i32),
+        voxels,
+        palette: Some(palette),
+    };
+
+    match fs::write(&path.0, model.to_bytes()) {
+        Ok(()) => info!("Exported {} blocks to {}", model.voxels.len(), path.0),
+        Err(err) => error!("Could not export to {}: {}", path.0, err),
+    }
+}
+
+/// Places the voxels of a `.vox` model on the floor at the origin, as a single undo step. Each
+/// color becomes the block type with the closest color, or the selected one without palette.
+fn import_vox(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    materials: Res<Assets<StandardMaterial>>,
+    selected: Res<SelectedBlock>,
+    path: Res<VoxPath>,
+    mut edits: EventWriter<EditBlocks>,
+) {
+    if !input_map.just_pressed(Action::ImportVox, &keys, &mouse) {
+        return;
+    }
+
+    let _span = info_span!("import_vox").entered();
+
+    let model = match VoxModel::load(&path.0) {
+        Ok(model) => model,
+        Err(err) => {
+            error!("Could not import {}: {}", path.0, err);
+            return;
+        }
+    };
+
+    let colors = type_colors(&registry, &materials);
+    let block_types: Vec<BlockType> = match &model.palette {
+        Some(palette) => palette
+            .iter()
+            .map(|&color| nearest_type(&colors, color).unwrap_or(selected.0))
+            .collect(),
+        None => vec![selected.0; 255],
+    };
+
+    let mut cells: Vec<_> = model
+        .voxels
+        .iter()
+        .filter_map(|&(x, y, z, color)| {
+            let position = BlockPosition {
+                x: x as i64,
+                y: z as i64 + 1,
+                z: -(y as i64),
+            };
+            let block_type = *block_types.get(color.checked_sub(1)? as usize)?;
+            position.in_bounds().then(|| (position, block_type))
+        })
+        .collect();
+    let skipped = model.voxels.len() - cells.len();
+
+    // Bottom layer first, so blocks needing support are placed after the ones below them.
+    cells.sort_by_key(|(position, _)| position.y);
+
+    info!(
+        "Importing {} blocks of a {}x{}x{} model from {}",
+        cells.len(),
+        model.size.0,
+        model.size.2,
+        model.size.1,
+        path.0
+    );
+    if skipped > 0 {
+        warn!("{} voxels out of the world were skipped", skipped);
+    }
+
+    edits.send(EditBlocks {
+        edits: cells
+            .into_iter()
+            .map(|(position, block_type)| BlockEdit::Place {
+                position,
+                block_type,
+            })
+            .collect(),
+        source: None,
+    });
+}
+
+/// Exports the world to a MagicaVoxel `.vox` file with Shift+E, and imports one with Shift+I.
+pub struct VoxPlugin;
+
+impl Plugin for VoxPlugin {
+    fn build(&self, app: &mut App) {
+        let path = cli_arg("--vox").unwrap_or_else(|| DEFAULT_VOX_PATH.to_string());
+
+        app.insert_resource(VoxPath(path))
+            .add_system(export_vox)
+            .add_system(import_vox);
+    }
+}
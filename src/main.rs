@@ -1,18 +1,103 @@
+use bevy::asset::AssetServerSettings;
+use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::PresentMode;
 
+mod analytics;
+mod block;
+mod block_material;
+mod blueprint;
+mod box_select;
+mod bucket;
 mod camera;
+mod chart;
+mod chunk;
+mod clipboard;
+mod compression;
+mod extrude;
+mod gesture;
+mod gizmos;
+mod guides;
+mod help;
+mod history;
+mod hotbar;
+mod input_map;
+mod io;
+mod map_table;
+mod mesher;
+mod mining;
+mod optimizer;
+mod palette;
+mod persistence;
+mod power;
+mod preview;
+mod profiler;
+mod quality;
+mod reference;
+mod regions;
+mod repeat_placement;
+mod scenario;
+mod selection;
+mod spawn;
+mod speed_build;
+mod stats;
+mod version;
+mod voxel_material;
+mod voxel_raycast;
+mod worldgen;
 
-use camera::GameCameraPlugin;
-
-use bevy_mod_raycast::{
-    DefaultPluginState, DefaultRaycastingPlugin, Intersection, RayCastMesh, RayCastMethod,
-    RayCastSource, RaycastSystem,
+use analytics::AnalyticsPlugin;
+use block::{
+    BlockAssets, BlockLight, BlockMap, BlockPlugin, BlockRegistry, BlockType, SelectedBlock,
 };
+use block_material::BlockMaterialPlugin;
+use blueprint::BlueprintPlugin;
+use box_select::{BoxSelectPlugin, ToolMode};
+use bucket::BucketPlugin;
+use camera::{CameraMode, GameCameraPlugin};
+use chart::ChartPlugin;
+use chunk::{ChunkPlugin, Chunks};
+use clipboard::ClipboardPlugin;
+use extrude::ExtrudePlugin;
+use gesture::GesturePlugin;
+use gizmos::GizmosPlugin;
+use guides::GuidesPlugin;
+use help::HelpPlugin;
+use history::{BlockEdit, EditBlocks, HistoryPlugin};
+use hotbar::HotbarPlugin;
+use input_map::{update_triggered_actions, Action, InputMap};
+use io::obj::ObjPlugin;
+use io::vox::VoxPlugin;
+use map_table::MapTablePlugin;
+use mining::{MiningPlugin, SurvivalLite};
+use palette::CommandPalettePlugin;
+use persistence::PersistencePlugin;
+use power::LowPowerPlugin;
+use preview::PlacementPreviewPlugin;
+use profiler::ProfilerPlugin;
+use quality::AdaptiveQualityPlugin;
+use reference::ReferencePlugin;
+use regions::RegionsPlugin;
+use repeat_placement::RepeatPlacementPlugin;
+use scenario::ScenarioPlugin;
+use selection::SelectionPlugin;
+use spawn::SpawnPlugin;
+use speed_build::SpeedBuildPlugin;
+use stats::StatsPlugin;
+use version::VersionPlugin;
+use voxel_material::VoxelMaterialPlugin;
+use voxel_raycast::{PointedFace, VoxelRaycastPlugin};
+use worldgen::WorldGenPlugin;
+
+use bevy_mod_raycast::{RayCastMethod, RayCastSource};
 
 const GRID_SIZE: u64 = 5;
+/// Blocks can only be placed within this distance of the origin, on every axis.
+const WORLD_LIMIT: i64 = 64;
+/// Mouse movement, in pixels, above which a press is a drag rather than a click.
+const CLICK_MAX_DRAG: f32 = 4.0;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct BlockPosition {
     x: i64,
     y: i64,
@@ -23,14 +108,35 @@ impl BlockPosition {
     pub fn into_transform(&self) -> Transform {
         Transform::from_xyz(self.x as f32, self.y as f32, self.z as f32)
     }
+
+    pub fn in_bounds(&self) -> bool {
+        self.x.abs() <= WORLD_LIMIT && self.y.abs() <= WORLD_LIMIT && self.z.abs() <= WORLD_LIMIT
+    }
 }
 
+/// Tags the floor tiles spawned in `setup`, as opposed to blocks placed by the player.
+#[derive(Component)]
+struct FloorTile;
+
 struct MyRaycastSet;
 
+/// Returns the value following `flag` on the command line, e.g. `--scenario <path>`.
+fn cli_arg(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    args.next()
+}
+
 fn update_raycast_with_cursor(
+    mode: Res<CameraMode>,
     mut cursor: EventReader<CursorMoved>,
     mut query: Query<&mut RayCastSource<MyRaycastSet>>,
 ) {
+    // The fly camera picks along its view direction instead.
+    if *mode == CameraMode::Fly {
+        return;
+    }
+
     // Grab the most recent cursor event if it exists:
     let cursor_position = match cursor.iter().last() {
         Some(cursor_moved) => cursor_moved.position,
@@ -42,95 +148,223 @@ fn update_raycast_with_cursor(
     }
 }
 
-fn new_cube_from_raycast(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mouse_input: Res<Input<MouseButton>>,
-    query: Query<&Intersection<MyRaycastSet>>,
-) {
-    let intersection = query.get_single().ok();
+/// Computes the cell of a new block placed against the face hit at `position`.
+fn block_position_from_hit(position: Vec3, normal: Vec3) -> BlockPosition {
+    let mut offset_x = 0.0;
+    let mut offset_y = 0.0;
+    let mut offset_z = 0.0;
 
-    if let Some((position, normal)) = intersection.and_then(|i| Some((i.position()?, i.normal()?)))
-    {
-        if !mouse_input.just_pressed(MouseButton::Left) {
-            return;
-        }
+    // Using normal direction to put new cube next/below/over to the intersected one
+    // without the need to know which one is intersected.
+    if normal.x > 0.0 {
+        offset_x = 0.5;
+    } else if normal.x < 0.0 {
+        offset_x = -0.51;
+    }
 
-        let mut offset_x = 0.0;
-        let mut offset_y = 0.0;
-        let mut offset_z = 0.0;
+    if normal.y > 0.0 {
+        offset_y = 0.5;
+    } else if normal.y < 0.0 {
+        offset_y = -0.51;
+    }
 
-        // Using normal direction to put new cube next/below/over to the intersected one
-        // without the need to know which one is intersected.
-        if normal.x > 0.0 {
-            offset_x = 0.5;
-        } else if normal.x < 0.0 {
-            offset_x = -0.51;
-        }
+    if normal.z > 0.0 {
+        offset_z = 0.5;
+    } else if normal.z < 0.0 {
+        offset_z = -0.51;
+    }
 
-        if normal.y > 0.0 {
-            offset_y = 0.5;
-        } else if normal.y < 0.0 {
-            offset_y = -0.51;
-        }
+    let mut rough_cube_position =
+        position + Vec3::new(offset_x, offset_y, offset_z) + Vec3::new(0.50, 0.50, 0.50);
 
-        if normal.z > 0.0 {
-            offset_z = 0.5;
-        } else if normal.z < 0.0 {
-            offset_z = -0.51;
-        }
+    // If the pos on an axis is negative, rounding will occur in the incorrect way.
+    if rough_cube_position.x < 0.0 {
+        rough_cube_position.x -= 1.0;
+    }
+
+    if rough_cube_position.y < 0.0 {
+        rough_cube_position.y -= 1.0;
+    }
 
-        let mut rough_cube_position =
-            *position + Vec3::new(offset_x, offset_y, offset_z) + Vec3::new(0.50, 0.50, 0.50);
+    if rough_cube_position.z < 0.0 {
+        rough_cube_position.z -= 1.0;
+    }
 
-        // If the pos on an axis is negative, rounding will occur in the incorrect way.
-        if rough_cube_position.x < 0.0 {
-            rough_cube_position.x -= 1.0;
-        }
+    // Rounding takes care of the good positionning of the cube
+    BlockPosition {
+        x: rough_cube_position.x as i64,
+        y: rough_cube_position.y as i64,
+        z: rough_cube_position.z as i64,
+    }
+}
+
+/// Block or floor tile under the cursor, and its cell.
+fn pointed_block(pointed: &PointedFace, map: &BlockMap) -> Option<(Entity, BlockPosition)> {
+    let position = pointed.0?.cell;
+
+    Some((map.get(position)?, position))
+}
 
-        if rough_cube_position.y < 0.0 {
-            rough_cube_position.y -= 1.0;
+/// Stores a block in its chunk and spawns the entity holding its components.
+fn spawn_cube(
+    commands: &mut Commands,
+    registry: &BlockRegistry,
+    chunks: &mut Chunks,
+    cube_position: BlockPosition,
+    block_type: BlockType,
+) -> Option<Entity> {
+    let _span = info_span!("spawn_cube").entered();
+
+    let definition = match registry.get(block_type) {
+        Some(definition) => definition,
+        None => {
+            warn!("Unknown block type {:?}", block_type);
+            return None;
         }
+    };
+
+    let cube_transform = cube_position.into_transform();
+
+    chunks.set(cube_position, Some(block_type));
+
+    // Drawn by the chunk, but still placed for its children like lights.
+    let mut cube = commands.spawn_bundle(SpatialBundle {
+        transform: cube_transform,
+        ..default()
+    });
+    cube.insert(cube_position).insert(block_type);
 
-        if rough_cube_position.z < 0.0 {
-            rough_cube_position.z -= 1.0;
+    if let Some(color) = definition.light {
+        cube.with_children(|parent| {
+            parent
+                .spawn_bundle(PointLightBundle {
+                    point_light: PointLight {
+                        color,
+                        intensity: 200.0,
+                        range: 8.0,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(BlockLight);
+        });
+    }
+
+    Some(cube.id())
+}
+
+fn new_cube_from_raycast(
+    mut edits: EventWriter<EditBlocks>,
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    map: Res<BlockMap>,
+    mouse_input: Res<Input<MouseButton>>,
+    key_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mode: Res<ToolMode>,
+    pointed: Res<PointedFace>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    if *mode != ToolMode::Build {
+        return;
+    }
+
+    if !input_map.just_pressed(Action::PlaceBlock, &key_input, &mouse_input) {
+        return;
+    }
+
+    if let Some(hit) = pointed.0 {
+        let cube_position = block_position_from_hit(hit.position, hit.normal);
+        if !registry.can_place(selected.0, cube_position, &map) {
+            return;
         }
 
-        // Rounding takes care of the good positionning of the cube
-        let cube_position = BlockPosition {
-            x: rough_cube_position.x as i64,
-            y: rough_cube_position.y as i64,
-            z: rough_cube_position.z as i64,
-        };
-
-        let cube_transform = cube_position.into_transform();
-
-        commands
-            .spawn_bundle(PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
-                material: materials.add(Color::rgb(0.8, 0.8, 0.8).into()),
-                transform: cube_transform,
-                ..default()
-            })
-            .insert(cube_position)
-            .insert(RayCastMesh::<MyRaycastSet>::default());
+        edits.send(EditBlocks {
+            edits: vec![BlockEdit::Place {
+                position: cube_position,
+                block_type: selected.0,
+            }],
+            source: player.get_single().ok(),
+        });
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+/// Removes the pointed block when the remove button is clicked without dragging.
+///
+/// Blocks at floor level or below, floor tiles included, are only removed with
+/// `Action::RemoveFloorBlock`. In survival-lite mode, blocks are mined instead.
+fn remove_cube_from_raycast(
+    mut edits: EventWriter<EditBlocks>,
+    survival: Res<SurvivalLite>,
+    camera_mode: Res<CameraMode>,
+    mouse_input: Res<Input<MouseButton>>,
+    key_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut motion: EventReader<MouseMotion>,
+    mut click: Local<Option<(Action, f32)>>,
+    map: Res<BlockMap>,
+    pointed: Res<PointedFace>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
 ) {
-    commands.insert_resource(DefaultPluginState::<MyRaycastSet>::default().with_debug_cursor());
+    if survival.enabled {
+        return;
+    }
 
-    let floor_tile = PbrBundle {
-        mesh: meshes.add(Mesh::from(shape::Plane { size: 1.0 })),
-        material: materials.add(Color::rgb(0.1, 0.8, 0.1).into()),
-        ..default()
+    if input_map.just_pressed(Action::RemoveFloorBlock, &key_input, &mouse_input) {
+        *click = Some((Action::RemoveFloorBlock, 0.0));
+    } else if input_map.just_pressed(Action::RemoveBlock, &key_input, &mouse_input) {
+        *click = Some((Action::RemoveBlock, 0.0));
+    }
+
+    let (action, drag) = match click.as_mut() {
+        Some((action, drag)) => (*action, drag),
+        None => return,
     };
 
+    // The remove button may also be bound to a camera drag, so dragging cancels the removal.
+    *drag += motion.iter().map(|event| event.delta.length()).sum::<f32>();
+
+    if !input_map.just_released(action, &key_input, &mouse_input) {
+        return;
+    }
+
+    // Mouse look moves the locked cursor all the time while flying, and nothing is dragged then.
+    let clicked = *camera_mode == CameraMode::Fly || *drag < CLICK_MAX_DRAG;
+    *click = None;
+
+    if !clicked {
+        return;
+    }
+
+    if let Some((_, position)) = pointed_block(&pointed, &map) {
+        if position.y > 0 || action == Action::RemoveFloorBlock {
+            edits.send(EditBlocks {
+                edits: vec![BlockEdit::Remove { position }],
+                source: player.get_single().ok(),
+            });
+        }
+    }
+}
+
+/// Spawns a floor tile, drawn on its own rather than by the chunks.
+fn spawn_floor_tile(
+    commands: &mut Commands,
+    assets: &BlockAssets,
+    position: BlockPosition,
+) -> Entity {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: assets.floor_tile.clone(),
+            material: assets.floor_material.clone(),
+            transform: position.into_transform(),
+            ..default()
+        })
+        .insert(position)
+        .insert(FloorTile)
+        .id()
+}
+
+fn setup(mut commands: Commands, assets: Res<BlockAssets>) {
     for x in 0..GRID_SIZE {
         for z in 0..GRID_SIZE {
             let position = BlockPosition {
@@ -138,16 +372,7 @@ fn setup(
                 y: 0,
                 z: z as i64,
             };
-
-            let floor_tile = PbrBundle {
-                transform: position.into_transform(),
-                ..floor_tile.clone()
-            };
-
-            commands
-                .spawn_bundle(floor_tile.clone())
-                .insert(position)
-                .insert(RayCastMesh::<MyRaycastSet>::default());
+            spawn_floor_tile(&mut commands, &assets, position);
         }
     }
 
@@ -155,42 +380,69 @@ fn setup(
         transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
         ..Default::default()
     });
-
-    // Small cubes to indicate directions
-    commands.spawn_bundle(PbrBundle {
-        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
-        material: materials.add(Color::rgb(1.0, 0.0, 0.0).into()),
-        transform: Transform::from_xyz(5.0, 0.0, 0.0),
-        ..default()
-    });
-    commands.spawn_bundle(PbrBundle {
-        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
-        material: materials.add(Color::rgb(0.0, 1.0, 0.0).into()),
-        transform: Transform::from_xyz(0.0, 5.0, 0.0),
-        ..default()
-    });
-    commands.spawn_bundle(PbrBundle {
-        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
-        material: materials.add(Color::rgb(0.0, 0.0, 1.0).into()),
-        transform: Transform::from_xyz(0.0, 0.0, 5.0),
-        ..default()
-    });
 }
 
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some(optimizer::SUBCOMMAND) {
+        optimizer::run();
+        return;
+    }
+
     App::new()
         .insert_resource(WindowDescriptor {
             present_mode: PresentMode::AutoNoVsync, // Reduces input lag.
             ..Default::default()
         })
+        // Reloads the voxel shader when it is saved.
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: voxel_material::enabled(),
+            ..default()
+        })
         .add_plugins(DefaultPlugins)
-        .add_plugin(DefaultRaycastingPlugin::<MyRaycastSet>::default())
+        .init_resource::<InputMap>()
+        .add_system_to_stage(CoreStage::First, update_triggered_actions)
+        .add_plugin(VoxelRaycastPlugin)
+        .add_plugin(BlockPlugin)
+        .add_plugin(ChunkPlugin)
+        .add_plugin(BlockMaterialPlugin)
+        .add_plugin(VoxelMaterialPlugin)
+        .add_plugin(PersistencePlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(VoxPlugin)
+        .add_plugin(ObjPlugin)
+        .add_plugin(WorldGenPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(MiningPlugin)
         .add_plugin(GameCameraPlugin)
-        .add_system_to_stage(
-            CoreStage::First,
-            update_raycast_with_cursor.before(RaycastSystem::BuildRays::<MyRaycastSet>),
-        )
+        .add_plugin(HelpPlugin)
+        .add_plugin(HotbarPlugin)
+        .add_plugin(CommandPalettePlugin)
+        .add_plugin(ProfilerPlugin)
+        .add_plugin(AnalyticsPlugin)
+        .add_plugin(VersionPlugin)
+        .add_plugin(LowPowerPlugin)
+        .add_plugin(AdaptiveQualityPlugin)
+        .add_plugin(GizmosPlugin)
+        .add_plugin(GuidesPlugin)
+        .add_plugin(GesturePlugin)
+        .add_plugin(SelectionPlugin)
+        .add_plugin(BoxSelectPlugin)
+        .add_plugin(ClipboardPlugin)
+        .add_plugin(BucketPlugin)
+        .add_plugin(RegionsPlugin)
+        .add_plugin(SpawnPlugin)
+        .add_plugin(PlacementPreviewPlugin)
+        .add_plugin(RepeatPlacementPlugin)
+        .add_plugin(ExtrudePlugin)
+        .add_plugin(ScenarioPlugin)
+        .add_plugin(BlueprintPlugin)
+        .add_plugin(ChartPlugin)
+        .add_plugin(ReferencePlugin)
+        .add_plugin(MapTablePlugin)
+        .add_plugin(SpeedBuildPlugin)
+        .add_system_to_stage(CoreStage::First, update_raycast_with_cursor)
         .add_startup_system(setup)
         .add_system(new_cube_from_raycast)
+        .add_system(remove_cube_from_raycast)
         .run();
 }
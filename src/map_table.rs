@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::ui::UiCameraConfig;
+
+use crate::block::{BlockAssets, BlockMap};
+use crate::block_position_from_hit;
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+
+/// Size of the map texture, in pixels.
+const MAP_RESOLUTION: u32 = 256;
+/// Height of the map camera above its table, in cells.
+const MAP_HEIGHT: f32 = 40.0;
+/// Seconds between two renders of the maps, since they don't need to be smooth.
+const MAP_REFRESH_INTERVAL: f32 = 0.5;
+
+/// Block showing a live top-down view of its surroundings on its top face.
+#[derive(Component)]
+struct MapTable;
+
+/// Camera rendering the map of a table.
+#[derive(Component)]
+struct MapCamera {
+    table: Entity,
+}
+
+struct MapRefreshTimer(Timer);
+
+fn map_image(images: &mut Assets<Image>) -> Handle<Image> {
+    let size = Extent3d {
+        width: MAP_RESOLUTION,
+        height: MAP_RESOLUTION,
+        ..default()
+    };
+
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    // Fills the image with zeroes.
+    image.resize(size);
+
+    images.add(image)
+}
+
+fn place_map_table(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    assets: Res<BlockAssets>,
+    map: Res<BlockMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    pointed: Res<PointedFace>,
+) {
+    if !input_map.just_pressed(Action::PlaceMapTable, &keys, &mouse) {
+        return;
+    }
+
+    let hit = match pointed.0 {
+        Some(hit) => hit,
+        None => return,
+    };
+
+    let position = block_position_from_hit(hit.position, hit.normal);
+    if !position.in_bounds() || map.contains(position) {
+        return;
+    }
+
+    let transform = position.into_transform();
+    let image = map_image(&mut images);
+
+    let table = commands
+        .spawn_bundle(PbrBundle {
+            mesh: assets.cube.clone(),
+            material: materials.add(Color::rgb(0.45, 0.3, 0.15).into()),
+            transform,
+            ..default()
+        })
+        .insert(position)
+        .insert(MapTable)
+        .with_children(|parent| {
+            parent.spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Plane { size: 0.9 })),
+                material: materials.add(StandardMaterial {
+                    base_color_texture: Some(image.clone()),
+                    unlit: true,
+                    ..default()
+                }),
+                // Just above the top face.
+                transform: Transform::from_xyz(0.0, 0.501, 0.0),
+                ..default()
+            });
+        })
+        .id();
+
+    // North up, like the rest of the world seen from the default camera.
+    commands
+        .spawn_bundle(Camera3dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image),
+                priority: -1,
+                ..default()
+            },
+            transform: Transform::from_translation(transform.translation + Vec3::Y * MAP_HEIGHT)
+                .looking_at(transform.translation, Vec3::NEG_Z),
+            ..default()
+        })
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(MapCamera { table });
+}
+
+/// Only renders the maps every `MAP_REFRESH_INTERVAL`.
+fn refresh_maps(
+    time: Res<Time>,
+    mut timer: ResMut<MapRefreshTimer>,
+    mut cameras: Query<&mut Camera, With<MapCamera>>,
+) {
+    let refresh = timer.0.tick(time.delta()).just_finished();
+
+    for mut camera in &mut cameras {
+        if camera.is_active != refresh {
+            camera.is_active = refresh;
+        }
+    }
+}
+
+/// Removes the cameras of tables that were removed.
+fn despawn_map_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &MapCamera)>,
+    tables: Query<(), With<MapTable>>,
+) {
+    for (entity, camera) in &cameras {
+        if tables.get(camera.table).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Map table blocks, showing a live top-down view of the area around them.
+pub struct MapTablePlugin;
+
+impl Plugin for MapTablePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MapRefreshTimer(Timer::from_seconds(
+            MAP_REFRESH_INTERVAL,
+            true,
+        )))
+        .add_system(place_map_table)
+        .add_system(refresh_maps)
+        .add_system(despawn_map_cameras);
+    }
+}
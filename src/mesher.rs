@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, MeshVertexAttribute, PrimitiveTopology};
+use bevy::render::render_resource::VertexFormat;
+
+use crate::block::{BlockFaces, BlockRegistry, BlockType, UvRect};
+use crate::chunk::CHUNK_SIZE;
+
+/// Index of the block type of each vertex, to look up its color in the voxel material palette.
+pub const ATTRIBUTE_BLOCK_TYPE: MeshVertexAttribute =
+    MeshVertexAttribute::new("BlockType", 501_284_911, VertexFormat::Uint32);
+/// Light reaching each vertex, from 0 in a corner closed on all sides to 1 in the open.
+pub const ATTRIBUTE_OCCLUSION: MeshVertexAttribute =
+    MeshVertexAttribute::new("Occlusion", 501_284_912, VertexFormat::Float32);
+/// Area of the texture atlas drawn on each face, as its lowest then highest UV coordinates. The
+/// shaders repeat it along `Mesh::ATTRIBUTE_UV_0`, so that merged faces show it once per block.
+pub const ATTRIBUTE_ATLAS_RECT: MeshVertexAttribute =
+    MeshVertexAttribute::new("AtlasRect", 501_284_913, VertexFormat::Float32x4);
+
+const SIZE: usize = CHUNK_SIZE as usize;
+const AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+/// Corners of a face, along its two axes, counter-clockwise.
+const CORNERS: [(i64, i64); 4] = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+
+/// Faces of a cube, as the axis of their normal, its sign, and the two axes along the face. The
+/// axes along the face are ordered so that their cross product is the normal, which keeps every
+/// face counter-clockwise seen from outside.
+const FACES: [(usize, i64, usize, usize); 6] = [
+    (0, 1, 1, 2),
+    (0, -1, 2, 1),
+    (1, 1, 2, 0),
+    (1, -1, 0, 2),
+    (2, 1, 0, 1),
+    (2, -1, 1, 0),
+];
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    block_types: Vec<u32>,
+    occlusion: Vec<f32>,
+    atlas_rects: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    /// Adds a rectangle centered on `center`, with sides `u` and `v`, and the occlusion of each
+    /// of its corners in the order of `CORNERS`, showing `rect` of the atlas once per block.
+    #[allow(clippy::too_many_arguments)]
+    fn push_quad(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        u: Vec3,
+        v: Vec3,
+        block_type: BlockType,
+        occlusion: [u8; 4],
+        rect: UvRect,
+    ) {
+        let start = self.positions.len() as u32;
+        let size = (u + v).abs();
+        let min = center - size / 2.0;
+
+        for ((du, dv), occlusion) in CORNERS.into_iter().zip(occlusion) {
+            let (du, dv) = (du as f32 / 2.0, dv as f32 / 2.0);
+            let position = center + u * du + v * dv;
+            self.positions.push(position.to_array());
+            self.normals.push(normal.to_array());
+            self.uvs.push(face_uv(normal, position - min, size));
+            self.block_types.push(block_type.0 as u32);
+            self.occlusion.push(occlusion as f32 / 3.0);
+            self.atlas_rects
+                .push([rect.min.x, rect.min.y, rect.max.x, rect.max.y]);
+        }
+
+        // Splits along the brightest diagonal, so that occlusion is interpolated evenly.
+        let [a, b, c, d] = occlusion;
+        let corners = if a + c < b + d {
+            [1, 2, 3, 1, 3, 0]
+        } else {
+            [0, 1, 2, 0, 2, 3]
+        };
+        self.indices
+            .extend(corners.into_iter().map(|corner| start + corner));
+    }
+
+    fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(ATTRIBUTE_BLOCK_TYPE, self.block_types);
+        mesh.insert_attribute(ATTRIBUTE_OCCLUSION, self.occlusion);
+        mesh.insert_attribute(ATTRIBUTE_ATLAS_RECT, self.atlas_rects);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
+
+/// UV of a point of a rectangle with `normal`, `offset` from its lowest corner, `size` being its
+/// extent along each axis.
+///
+/// One unit per block, so that the atlas tile is drawn once per block over merged faces. Side faces
+/// are drawn with the top of their tile up.
+fn face_uv(normal: Vec3, offset: Vec3, size: Vec3) -> [f32; 2] {
+    if normal.y != 0.0 {
+        [offset.x, offset.z]
+    } else if normal.x != 0.0 {
+        [offset.z, size.y - offset.y]
+    } else {
+        [offset.x, size.y - offset.y]
+    }
+}
+
+/// Meshes of a chunk, one per material.
+#[derive(Default)]
+pub struct ChunkMeshes {
+    pub opaque: Option<Mesh>,
+    /// Faces of the transparent types, drawn with blending.
+    pub transparent: Option<Mesh>,
+}
+
+/// Builds the meshes of a chunk without the faces hidden by other blocks, merging the coplanar
+/// faces of a same type into larger rectangles (greedy meshing).
+pub struct ChunkMesher {
+    /// Types letting the faces behind them show through.
+    transparent: HashSet<BlockType>,
+    /// Whether to darken the corners of faces next to other blocks (ambient occlusion). Faces
+    /// only merge when their corners match, so it is only computed when it is drawn.
+    occlusion: bool,
+    /// Atlas tiles of the textured types, the others being drawn with `UvRect::plain`.
+    faces: HashMap<BlockType, BlockFaces>,
+}
+
+impl ChunkMesher {
+    pub fn new(registry: &BlockRegistry, occlusion: bool) -> Self {
+        let transparent = registry
+            .types()
+            .filter(|&block_type| {
+                registry
+                    .get(block_type)
+                    .map_or(false, |definition| definition.transparent)
+            })
+            .collect();
+
+        let faces = registry
+            .types()
+            .filter_map(|block_type| Some((block_type, registry.get(block_type)?.faces?)))
+            .collect();
+
+        ChunkMesher {
+            transparent,
+            occlusion,
+            faces,
+        }
+    }
+
+    fn is_opaque(&self, block_type: Option<BlockType>) -> bool {
+        block_type.map_or(false, |block_type| !self.transparent.contains(&block_type))
+    }
+
+    /// Occlusion of the corners of a face of `cell`, from 0 when closed by blocks on both sides to
+    /// 3 when open, in the order of `CORNERS`.
+    fn face_occlusion(
+        &self,
+        block: &impl Fn([i64; 3]) -> Option<BlockType>,
+        cell: [i64; 3],
+        (d, sign, u, v): (usize, i64, usize, usize),
+    ) -> [u8; 4] {
+        if !self.occlusion {
+            return [3; 4];
+        }
+
+        let opaque_at = |du: i64, dv: i64| {
+            let mut neighbour = cell;
+            neighbour[d] += sign;
+            neighbour[u] += du;
+            neighbour[v] += dv;
+            self.is_opaque(block(neighbour)) as u8
+        };
+
+        CORNERS.map(|(du, dv)| {
+            let (side_u, side_v) = (opaque_at(du, 0), opaque_at(0, dv));
+            if side_u + side_v == 2 {
+                0
+            } else {
+                3 - side_u - side_v - opaque_at(du, dv)
+            }
+        })
+    }
+
+    /// A face is hidden by an opaque block, or by a block of the same type so that a wall of glass
+    /// doesn't show its inner faces.
+    fn is_hidden(&self, block_type: BlockType, neighbour: Option<BlockType>) -> bool {
+        match neighbour {
+            Some(neighbour) => neighbour == block_type || !self.transparent.contains(&neighbour),
+            None => false,
+        }
+    }
+
+    fn faces(&self, block_type: BlockType) -> BlockFaces {
+        self.faces
+            .get(&block_type)
+            .copied()
+            .unwrap_or_else(|| BlockFaces::all(UvRect::plain()))
+    }
+
+    /// The opaque and transparent meshes of the chunk, relative to its corner.
+    ///
+    /// `block` gives the type at a position relative to the corner. It is also called just outside
+    /// of the chunk, to cull the faces hidden by the neighbouring chunks.
+    pub fn mesh(&self, block: impl Fn([i64; 3]) -> Option<BlockType>) -> ChunkMeshes {
+        let mut opaque = MeshBuilder::default();
+        let mut transparent = MeshBuilder::default();
+        // Visible faces of a slice of the chunk, indexed by their position along `u` and `v`.
+        let mut mask = [None; SIZE * SIZE];
+
+        for face in FACES {
+            let (d, sign, u, v) = face;
+            for slice in 0..SIZE {
+                for b in 0..SIZE {
+                    for a in 0..SIZE {
+                        let mut cell = [0; 3];
+                        cell[d] = slice as i64;
+                        cell[u] = a as i64;
+                        cell[v] = b as i64;
+
+                        let mut neighbour = cell;
+                        neighbour[d] += sign;
+
+                        mask[b * SIZE + a] = block(cell)
+                            .filter(|&block_type| !self.is_hidden(block_type, block(neighbour)))
+                            .map(|block_type| {
+                                (block_type, self.face_occlusion(&block, cell, face))
+                            });
+                    }
+                }
+
+                // Grows each face along `u`, then along `v` while whole rows match, type and
+                // occlusion alike.
+                for b in 0..SIZE {
+                    let mut a = 0;
+                    while a < SIZE {
+                        let quad = match mask[b * SIZE + a] {
+                            Some(quad) => quad,
+                            None => {
+                                a += 1;
+                                continue;
+                            }
+                        };
+
+                        let mut width = 1;
+                        while a + width < SIZE && mask[b * SIZE + a + width] == Some(quad) {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        while b + height < SIZE
+                            && mask[(b + height) * SIZE + a..(b + height) * SIZE + a + width]
+                                .iter()
+                                .all(|&other| other == Some(quad))
+                        {
+                            height += 1;
+                        }
+
+                        for row in b..b + height {
+                            mask[row * SIZE + a..row * SIZE + a + width].fill(None);
+                        }
+
+                        let mut center = Vec3::ZERO;
+                        center[d] = slice as f32 + sign as f32 * 0.5;
+                        center[u] = a as f32 + (width - 1) as f32 / 2.0;
+                        center[v] = b as f32 + (height - 1) as f32 / 2.0;
+
+                        let (block_type, occlusion) = quad;
+                        let normal = AXES[d] * sign as f32;
+                        let builder = if self.transparent.contains(&block_type) {
+                            &mut transparent
+                        } else {
+                            &mut opaque
+                        };
+                        builder.push_quad(
+                            center,
+                            normal,
+                            AXES[u] * width as f32,
+                            AXES[v] * height as f32,
+                            block_type,
+                            occlusion,
+                            self.faces(block_type).rect(normal),
+                        );
+
+                        a += width;
+                    }
+                }
+            }
+        }
+
+        let build = |builder: MeshBuilder| (!builder.positions.is_empty()).then(|| builder.build());
+        ChunkMeshes {
+            opaque: build(opaque),
+            transparent: build(transparent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use super::*;
+
+    const GLASS: BlockType = BlockType(2);
+
+    fn mesher() -> ChunkMesher {
+        ChunkMesher {
+            transparent: HashSet::from([GLASS]),
+            occlusion: false,
+            faces: HashMap::from([(
+                BlockType::DIRT,
+                BlockFaces {
+                    top: UvRect::tile(0, 0),
+                    side: UvRect::tile(1, 0),
+                    bottom: UvRect::tile(2, 0),
+                },
+            )]),
+        }
+    }
+
+    fn mesh_blocks(blocks: &[([i64; 3], BlockType)]) -> ChunkMeshes {
+        mesher().mesh(|cell| {
+            blocks
+                .iter()
+                .find(|(position, _)| *position == cell)
+                .map(|(_, block_type)| *block_type)
+        })
+    }
+
+    fn vertex_types(mesh: &Mesh) -> Vec<BlockType> {
+        match mesh.attribute(ATTRIBUTE_BLOCK_TYPE) {
+            Some(VertexAttributeValues::Uint32(types)) => types
+                .iter()
+                .map(|&block_type| BlockType(block_type as u16))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn quads(meshes: &ChunkMeshes, block_type: BlockType) -> usize {
+        let vertices = [&meshes.opaque, &meshes.transparent]
+            .into_iter()
+            .flatten()
+            .flat_map(vertex_types)
+            .filter(|&other| other == block_type)
+            .count();
+        vertices / 4
+    }
+
+    #[test]
+    fn lone_cube() {
+        let meshes = mesh_blocks(&[([0, 0, 0], BlockType::STONE)]);
+        let mesh = meshes.opaque.as_ref().unwrap();
+
+        assert!(meshes.transparent.is_none());
+        assert_eq!(mesh.count_vertices(), 24);
+        assert_eq!(mesh.indices().map(|indices| indices.len()), Some(36));
+    }
+
+    #[test]
+    fn same_type_neighbours_merge() {
+        let meshes = mesh_blocks(&[([0, 0, 0], BlockType::STONE), ([1, 0, 0], BlockType::STONE)]);
+
+        // One quad per side of the 2x1x1 box.
+        assert_eq!(quads(&meshes, BlockType::STONE), 6);
+    }
+
+    #[test]
+    fn merged_faces_repeat_uvs() {
+        let meshes = mesh_blocks(&[([0, 0, 0], BlockType::DIRT), ([1, 0, 0], BlockType::DIRT)]);
+        let uvs = match meshes.opaque.unwrap().attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => uvs.clone(),
+            _ => panic!("mesh has no UVs"),
+        };
+
+        // One unit per block along the two blocks.
+        let max_u = uvs.iter().map(|[u, _]| *u).fold(0.0, f32::max);
+        let max_v = uvs.iter().map(|[_, v]| *v).fold(0.0, f32::max);
+        assert_eq!((max_u, max_v), (2.0, 1.0));
+    }
+
+    #[test]
+    fn atlas_rect_per_face() {
+        let meshes = mesh_blocks(&[([0, 0, 0], BlockType::DIRT), ([0, 0, 1], BlockType::STONE)]);
+        let mesh = meshes.opaque.unwrap();
+        let (normals, rects) = match (
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            mesh.attribute(ATTRIBUTE_ATLAS_RECT),
+        ) {
+            (
+                Some(VertexAttributeValues::Float32x3(normals)),
+                Some(VertexAttributeValues::Float32x4(rects)),
+            ) => (normals.clone(), rects.clone()),
+            _ => panic!("mesh has no normals or atlas rects"),
+        };
+        let rect_of = |rect: UvRect| [rect.min.x, rect.min.y, rect.max.x, rect.max.y];
+
+        for ((normal, rect), block_type) in normals.iter().zip(&rects).zip(vertex_types(&mesh)) {
+            let expected = match (block_type, normal[1]) {
+                (BlockType::DIRT, y) if y > 0.0 => UvRect::tile(0, 0),
+                (BlockType::DIRT, y) if y < 0.0 => UvRect::tile(2, 0),
+                (BlockType::DIRT, _) => UvRect::tile(1, 0),
+                // Untextured in this mesher.
+                _ => UvRect::plain(),
+            };
+            assert_eq!(*rect, rect_of(expected));
+        }
+    }
+
+    #[test]
+    fn face_between_opaque_blocks_hidden() {
+        let meshes = mesh_blocks(&[([0, 0, 0], BlockType::DIRT), ([1, 0, 0], BlockType::STONE)]);
+
+        assert_eq!(quads(&meshes, BlockType::DIRT), 5);
+        assert_eq!(quads(&meshes, BlockType::STONE), 5);
+    }
+
+    #[test]
+    fn glass_next_to_glass() {
+        let meshes = mesh_blocks(&[([0, 0, 0], GLASS), ([1, 0, 0], GLASS)]);
+
+        assert!(meshes.opaque.is_none());
+        assert_eq!(quads(&meshes, GLASS), 6);
+    }
+
+    #[test]
+    fn glass_next_to_stone() {
+        let meshes = mesh_blocks(&[([0, 0, 0], GLASS), ([1, 0, 0], BlockType::STONE)]);
+
+        // The stone hides the glass face against it, but shows through the glass.
+        assert_eq!(quads(&meshes, GLASS), 5);
+        assert_eq!(quads(&meshes, BlockType::STONE), 6);
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockMap, BlockRegistry, BlockType};
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{pointed_block, BlockPosition, MyRaycastSet};
+
+/// Seconds to break blocks without a type, like map tables.
+const DEFAULT_HARDNESS: f32 = 1.0;
+/// Opacity of the crack overlay right before the block breaks.
+const MAX_CRACK_ALPHA: f32 = 0.8;
+
+/// Survival-lite mode, enabled with `--survival`: blocks are mined by holding the remove button
+/// for as long as their hardness, instead of being removed on click.
+pub struct SurvivalLite {
+    pub enabled: bool,
+}
+
+/// Block being mined and for how long.
+struct Mining {
+    target: Entity,
+    elapsed: f32,
+}
+
+/// Darkens the block being mined as it cracks.
+#[derive(Component)]
+struct CrackOverlay;
+
+fn setup_crack_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // Slightly bigger than a block so it wraps around it.
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.02 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.0, 0.0, 0.0, 0.0),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(CrackOverlay);
+}
+
+fn mine_block(
+    mut edits: EventWriter<EditBlocks>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    map: Res<BlockMap>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut mining: Local<Option<Mining>>,
+    pointed: Res<PointedFace>,
+    blocks: Query<(&BlockPosition, Option<&BlockType>)>,
+    mut overlay: Query<
+        (&mut Transform, &mut Visibility, &Handle<StandardMaterial>),
+        With<CrackOverlay>,
+    >,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    let floor_allowed = input_map.pressed(Action::RemoveFloorBlock, &keys, &mouse);
+    let holding = floor_allowed || input_map.pressed(Action::RemoveBlock, &keys, &mouse);
+
+    let target = pointed_block(&pointed, &map)
+        .and_then(|(entity, _)| Some((entity, blocks.get(entity).ok()?)))
+        .filter(|(_, (position, _))| position.y > 0 || floor_allowed);
+
+    // Progress is lost when letting go or pointing at another block.
+    let (entity, position, block_type) = match target {
+        Some((entity, (position, block_type))) if holding => (entity, *position, block_type),
+        _ => {
+            *mining = None;
+            for (_, mut visibility, _) in &mut overlay {
+                visibility.is_visible = false;
+            }
+            return;
+        }
+    };
+
+    if mining
+        .as_ref()
+        .map_or(false, |current| current.target != entity)
+    {
+        *mining = None;
+    }
+    let current = mining.get_or_insert(Mining {
+        target: entity,
+        elapsed: 0.0,
+    });
+    current.elapsed += time.delta_seconds();
+
+    let hardness = block_type
+        .and_then(|block_type| registry.get(*block_type))
+        .map_or(DEFAULT_HARDNESS, |definition| definition.hardness);
+    let progress = (current.elapsed / hardness).min(1.0);
+
+    if progress >= 1.0 {
+        edits.send(EditBlocks {
+            edits: vec![BlockEdit::Remove { position }],
+            source: player.get_single().ok(),
+        });
+        *mining = None;
+    }
+
+    for (mut transform, mut visibility, material) in &mut overlay {
+        visibility.is_visible = progress < 1.0;
+        *transform = position.into_transform();
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color.set_a(progress * MAX_CRACK_ALPHA);
+        }
+    }
+}
+
+/// Timed block removal based on the hardness of each block type, in survival-lite mode.
+pub struct MiningPlugin;
+
+impl Plugin for MiningPlugin {
+    fn build(&self, app: &mut App) {
+        let enabled = std::env::args().any(|arg| arg == "--survival");
+        app.insert_resource(SurvivalLite { enabled });
+
+        if enabled {
+            app.add_startup_system(setup_crack_overlay)
+                .add_system(mine_block);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::app::AppExit;
+use bevy::asset::AssetPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+use crate::block::BlockRegistry;
+use crate::compression;
+use crate::persistence::{optimize_world, world_path};
+
+/// Subcommand running the optimizer instead of the game, e.g. `optimize --world <path>`.
+pub const SUBCOMMAND: &str = "optimize";
+
+fn run_optimizer(registry: Res<BlockRegistry>, mut exit: EventWriter<AppExit>) {
+    let path = world_path();
+
+    match optimize_world(&path, &registry, compression::level()) {
+        Ok(report) => {
+            info!(
+                "Optimized {}: {} blocks kept, {} of unknown types pruned",
+                path, report.blocks, report.pruned_blocks
+            );
+            info!("{} autosaves merged", report.deltas);
+            info!(
+                "Size: {} bytes before, {} bytes after",
+                report.size_before, report.size_after
+            );
+        }
+        Err(err) => error!("Could not optimize world {}: {}", path, err),
+    }
+
+    exit.send(AppExit);
+}
+
+/// Rewrites the world save without opening a window: deltas are merged, blocks of types missing
+/// from the registry are dropped, and the result is recompressed at `--compression-level`.
+///
+/// Only the registry is needed from the game, whose materials need an asset server.
+pub fn run() {
+    App::new()
+        .add_plugins(MinimalPlugins)
+        .add_plugin(LogPlugin)
+        .add_plugin(AssetPlugin)
+        .add_asset::<StandardMaterial>()
+        .init_resource::<BlockRegistry>()
+        .add_startup_system(run_optimizer)
+        .run();
+}
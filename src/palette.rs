@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+
+use crate::input_map::{Action, InputMap};
+
+/// Number of matching actions listed under the search field.
+const MAX_RESULTS: usize = 8;
+
+/// Search typed in the open palette.
+#[derive(Default)]
+struct PaletteSearch {
+    query: String,
+    selected: usize,
+}
+
+#[derive(Component)]
+struct CommandPalette;
+
+/// Scores how well `query` matches `text`: every query character must appear in order, and
+/// consecutive characters and earlier matches score higher. Returns None if it doesn't match.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|t| *t == c)?;
+        score += if previous.map_or(false, |p| p + 1 == found) {
+            5
+        } else {
+            1
+        };
+        score -= found as i32 / 8;
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Actions matching the search, best first.
+fn search_results(input_map: &InputMap, query: &str) -> Vec<Action> {
+    let mut results: Vec<(i32, Action)> = input_map
+        .bindings()
+        .filter(|(action, _)| *action != Action::ToggleCommandPalette)
+        .filter_map(|(action, _)| Some((fuzzy_score(query, action.description())?, action)))
+        .collect();
+
+    // Stable, so ties keep the order of the bindings.
+    results.sort_by(|a, b| b.0.cmp(&a.0));
+    results
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, action)| action)
+        .collect()
+}
+
+fn text_style(asset_server: &AssetServer, color: Color) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/DejaVuSans.ttf"),
+        font_size: 22.0,
+        color,
+    }
+}
+
+fn open_command_palette(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut input_map: ResMut<InputMap>,
+    search: Option<Res<PaletteSearch>>,
+) {
+    if search.is_some() || !input_map.just_pressed(Action::ToggleCommandPalette, &keys, &mouse) {
+        return;
+    }
+
+    commands.init_resource::<PaletteSearch>();
+    input_map.suspended = true;
+}
+
+/// Types the search, Up and Down to pick an action, Enter to run it and Escape to close.
+fn type_command_search(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut input_map: ResMut<InputMap>,
+    search: Option<ResMut<PaletteSearch>>,
+) {
+    // Always drained, so the key that opened the palette isn't typed into the search.
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+
+    let mut search = match search {
+        Some(search) => search,
+        None => return,
+    };
+
+    for c in typed {
+        match c {
+            '\u{8}' => {
+                search.query.pop();
+            }
+            c if !c.is_control() => search.query.push(c),
+            _ => {}
+        }
+        search.selected = 0;
+    }
+
+    let results = search_results(&input_map, &search.query);
+
+    if keys.just_pressed(KeyCode::Down) && search.selected + 1 < results.len() {
+        search.selected += 1;
+    }
+    if keys.just_pressed(KeyCode::Up) && search.selected > 0 {
+        search.selected -= 1;
+    }
+
+    let done = keys.just_pressed(KeyCode::Return);
+    if done || keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<PaletteSearch>();
+        input_map.suspended = false;
+
+        if done {
+            if let Some(&action) = results.get(search.selected) {
+                input_map.trigger(action);
+            }
+        }
+    }
+}
+
+/// Rebuilds the palette when the search changes.
+fn refresh_command_palette(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    asset_server: Res<AssetServer>,
+    search: Option<Res<PaletteSearch>>,
+    query: Query<Entity, With<CommandPalette>>,
+) {
+    let changed = search
+        .as_ref()
+        .map_or(!query.is_empty(), |s| s.is_changed());
+    if !changed {
+        return;
+    }
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let search = match search {
+        Some(search) => search,
+        None => return,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(40.0),
+                    left: Val::Percent(30.0),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(40.0), Val::Auto),
+                // UI is laid out from the bottom, so reverse to list from the top.
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+            ..default()
+        })
+        .insert(CommandPalette)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                format!("> {}", search.query),
+                text_style(&asset_server, Color::WHITE),
+            ));
+
+            for (i, action) in search_results(&input_map, &search.query)
+                .into_iter()
+                .enumerate()
+            {
+                let color = if i == search.selected {
+                    Color::YELLOW
+                } else {
+                    Color::GRAY
+                };
+                let binding = input_map
+                    .binding(action)
+                    .map(|binding| binding.to_string())
+                    .unwrap_or_default();
+
+                parent.spawn_bundle(TextBundle::from_section(
+                    format!("{}  ({})", action.description(), binding),
+                    text_style(&asset_server, color),
+                ));
+            }
+        });
+}
+
+/// Fuzzy search over every action, opened with Ctrl+P, to run one without knowing its binding.
+pub struct CommandPalettePlugin;
+
+impl Plugin for CommandPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(open_command_palette)
+            .add_system(type_command_search.after(open_command_palette))
+            .add_system(refresh_command_palette.after(type_command_search));
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::fs;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::chunk::{ChunkPosition, Chunks};
+use crate::input_map::{Action, InputMap};
+use crate::{cli_arg, compression, spawn_cube, BlockPosition};
+
+const DEFAULT_WORLD_PATH: &str = "world.ron";
+/// Version written in new saves. Bump it when the format changes, and keep loading older ones.
+const SAVE_VERSION: u32 = 2;
+/// Version written in delta files. Deltas of another version are not applied, since they are
+/// compacted into the full save soon anyway.
+const DELTA_VERSION: u32 = 1;
+/// Seconds between two autosaves, only written if blocks changed in between.
+const AUTOSAVE_INTERVAL: f32 = 60.0;
+/// Autosaves written as deltas before the next one compacts them into a full save.
+const DELTAS_PER_FULL_SAVE: u32 = 10;
+
+/// Read first, to know which format the rest of the file is in. Delta files have one too.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBlock {
+    position: (i64, i64, i64),
+    block_type: u16,
+}
+
+/// Version 1: every placed block with its type. Floor tiles are not saved.
+#[derive(Serialize, Deserialize)]
+struct SaveV1 {
+    version: u32,
+    blocks: Vec<SavedBlock>,
+}
+
+/// Version 2: version 1 with the generation of the delta files applying on top of it.
+#[derive(Serialize, Deserialize)]
+struct SaveV2 {
+    version: u32,
+    generation: u64,
+    blocks: Vec<SavedBlock>,
+}
+
+/// The whole content of a chunk, replacing it in the save.
+#[derive(Serialize, Deserialize)]
+struct SavedChunk {
+    position: (i64, i64, i64),
+    blocks: Vec<SavedBlock>,
+}
+
+/// Chunks changed since the previous save or delta, written by autosaves.
+#[derive(Serialize, Deserialize)]
+struct SaveDelta {
+    version: u32,
+    chunks: Vec<SavedChunk>,
+}
+
+/// Where the world is saved and loaded from, set with `--world <path>`.
+///
+/// Autosaves only write the chunks changed since the previous one, to delta files named
+/// `<path>.<generation>.<n>.delta` and applied in order on top of the full save. Every
+/// `DELTAS_PER_FULL_SAVE` autosaves, or on `Action::SaveWorld`, they are compacted into a new full
+/// save of the next generation. Deltas left from an older generation, e.g. if the game closed while
+/// compacting, are ignored.
+pub struct WorldFile {
+    pub path: String,
+    /// Generation of the last full save.
+    generation: u64,
+    /// Delta files written since the last full save.
+    deltas: u32,
+    /// Zstd level of the written files, see `compression::level`.
+    compression_level: i32,
+    autosave: Timer,
+}
+
+/// Path of the world save, set with `--world <path>`.
+pub fn world_path() -> String {
+    cli_arg("--world").unwrap_or_else(|| DEFAULT_WORLD_PATH.to_string())
+}
+
+fn delta_path(path: &str, generation: u64, delta: u32) -> String {
+    format!("{}.{}.{}.delta", path, generation, delta)
+}
+
+/// A full save with its deltas applied.
+struct LoadedSave {
+    generation: u64,
+    /// Delta files applied.
+    deltas: u32,
+    /// Blocks grouped by chunk, since deltas replace whole chunks.
+    chunks: HashMap<ChunkPosition, Vec<SavedBlock>>,
+    /// Path and error of the delta that couldn't be read, if any. The following ones are skipped,
+    /// since they build on it.
+    broken_delta: Option<(String, Box<dyn std::error::Error>)>,
+}
+
+fn read_world(path: &str) -> Result<(u64, Vec<SavedBlock>), Box<dyn std::error::Error>> {
+    let content = compression::read_to_string(path)?;
+    let header: SaveHeader = ron::from_str(&content)?;
+
+    // Older versions are converted here as the format evolves.
+    match header.version {
+        1 => Ok((0, ron::from_str::<SaveV1>(&content)?.blocks)),
+        2 => {
+            let save = ron::from_str::<SaveV2>(&content)?;
+            Ok((save.generation, save.blocks))
+        }
+        version => Err(format!(
+            "save version {} is newer than the supported version {}",
+            version, SAVE_VERSION
+        )
+        .into()),
+    }
+}
+
+fn write_world(
+    path: &str,
+    generation: u64,
+    blocks: Vec<SavedBlock>,
+    compression_level: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let save = SaveV2 {
+        version: SAVE_VERSION,
+        generation,
+        blocks,
+    };
+    let content = ron::ser::to_string_pretty(&save, PrettyConfig::default())?;
+    compression::write(path, &content, compression_level)
+}
+
+fn read_delta(path: &str) -> Result<SaveDelta, Box<dyn std::error::Error>> {
+    let content = compression::read_to_string(path)?;
+    let header: SaveHeader = ron::from_str(&content)?;
+
+    if header.version != DELTA_VERSION {
+        return Err(format!(
+            "autosave version {} is not the supported version {}",
+            header.version, DELTA_VERSION
+        )
+        .into());
+    }
+
+    Ok(ron::from_str(&content)?)
+}
+
+/// Reads the full save at `path`, then applies its deltas in order.
+fn read_save(path: &str) -> Result<LoadedSave, Box<dyn std::error::Error>> {
+    let (generation, blocks) = read_world(path)?;
+
+    let mut chunks: HashMap<ChunkPosition, Vec<SavedBlock>> = HashMap::new();
+    for block in blocks {
+        let (x, y, z) = block.position;
+        let (chunk, _) = ChunkPosition::of(BlockPosition { x, y, z });
+        chunks.entry(chunk).or_default().push(block);
+    }
+
+    let mut deltas = 0;
+    let mut broken_delta = None;
+    loop {
+        let path = delta_path(path, generation, deltas + 1);
+        if fs::metadata(&path).is_err() {
+            break;
+        }
+
+        match read_delta(&path) {
+            Ok(delta) => {
+                for chunk in delta.chunks {
+                    let (x, y, z) = chunk.position;
+                    chunks.insert(ChunkPosition { x, y, z }, chunk.blocks);
+                }
+                deltas += 1;
+            }
+            Err(err) => {
+                broken_delta = Some((path, err));
+                break;
+            }
+        }
+    }
+
+    Ok(LoadedSave {
+        generation,
+        deltas,
+        chunks,
+        broken_delta,
+    })
+}
+
+fn write_delta(
+    path: &str,
+    delta: &SaveDelta,
+    compression_level: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Not pretty, since deltas are only read back by the game.
+    compression::write(path, &ron::to_string(delta)?, compression_level)
+}
+
+fn load_world(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    mut chunks: ResMut<Chunks>,
+    mut world: ResMut<WorldFile>,
+) {
+    // Starting without a save is the normal case for a new world.
+    if fs::metadata(&world.path).is_err() {
+        return;
+    }
+
+    let save = match read_save(&world.path) {
+        Ok(save) => save,
+        Err(err) => {
+            error!("Could not load world {}: {}", world.path, err);
+            return;
+        }
+    };
+
+    if let Some((path, err)) = &save.broken_delta {
+        error!("Could not load autosave {}: {}", path, err);
+    }
+
+    world.generation = save.generation;
+    world.deltas = save.deltas;
+
+    let mut count = 0;
+    for block in save.chunks.values().flatten() {
+        let (x, y, z) = block.position;
+        spawn_cube(
+            &mut commands,
+            &registry,
+            &mut chunks,
+            BlockPosition { x, y, z },
+            BlockType(block.block_type),
+        );
+        count += 1;
+    }
+    // The blocks just loaded are already saved.
+    chunks.clear_unsaved();
+
+    info!(
+        "Loaded {} blocks from {} and {} autosaves",
+        count, world.path, world.deltas
+    );
+}
+
+/// The blocks of a chunk, as saved.
+fn saved_blocks(chunks: &Chunks, chunk: ChunkPosition) -> Vec<SavedBlock> {
+    chunks
+        .blocks_in(chunk)
+        .map(|(position, block_type)| SavedBlock {
+            position: (position.x, position.y, position.z),
+            block_type: block_type.0,
+        })
+        .collect()
+}
+
+/// Writes every block to a new full save, then removes the deltas it replaces.
+///
+/// Blocks are read from `Chunks` rather than from their entities, like the deltas, so both agree
+/// on what the world holds.
+fn save_full(world: &mut WorldFile, chunks: &mut Chunks) {
+    let generation = world.generation + 1;
+    let blocks = chunks
+        .positions()
+        .flat_map(|chunk| saved_blocks(chunks, chunk))
+        .collect();
+
+    if let Err(err) = write_world(&world.path, generation, blocks, world.compression_level) {
+        error!("Could not save world {}: {}", world.path, err);
+        return;
+    }
+
+    for delta in 1..=world.deltas {
+        let _ = fs::remove_file(delta_path(&world.path, world.generation, delta));
+    }
+
+    world.generation = generation;
+    world.deltas = 0;
+    chunks.clear_unsaved();
+    info!("Saved world to {}", world.path);
+}
+
+/// Writes the chunks changed since the last save to the next delta file.
+fn save_delta(world: &mut WorldFile, chunks: &mut Chunks) {
+    let delta = SaveDelta {
+        version: DELTA_VERSION,
+        chunks: chunks
+            .unsaved()
+            .map(|chunk| SavedChunk {
+                position: (chunk.x, chunk.y, chunk.z),
+                blocks: saved_blocks(chunks, chunk),
+            })
+            .collect(),
+    };
+
+    let path = delta_path(&world.path, world.generation, world.deltas + 1);
+    match write_delta(&path, &delta, world.compression_level) {
+        Ok(()) => {
+            world.deltas += 1;
+            chunks.clear_unsaved();
+            info!("Autosaved {} chunks to {}", delta.chunks.len(), path);
+        }
+        Err(err) => error!("Could not autosave world to {}: {}", path, err),
+    }
+}
+
+/// What `optimize_world` changed in a save.
+pub struct OptimizeReport {
+    /// Blocks kept in the new save.
+    pub blocks: usize,
+    /// Blocks dropped because their type isn't in the registry anymore.
+    pub pruned_blocks: usize,
+    /// Deltas merged into the new save.
+    pub deltas: u32,
+    /// Size of the full save and its deltas, in bytes.
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Rewrites the save at `path` with its deltas merged and without blocks of unknown types, sorted
+/// and compressed at `compression_level`.
+///
+/// Nothing is written if a delta can't be read, since its blocks would be lost.
+pub fn optimize_world(
+    path: &str,
+    registry: &BlockRegistry,
+    compression_level: i32,
+) -> Result<OptimizeReport, Box<dyn std::error::Error>> {
+    let save = read_save(path)?;
+    if let Some((path, err)) = save.broken_delta {
+        return Err(format!("could not read autosave {}: {}", path, err).into());
+    }
+
+    let delta_paths: Vec<String> = (1..=save.deltas)
+        .map(|delta| delta_path(path, save.generation, delta))
+        .collect();
+    let mut size_before = fs::metadata(path)?.len();
+    for delta in &delta_paths {
+        size_before += fs::metadata(delta)?.len();
+    }
+
+    let mut blocks = Vec::new();
+    let mut pruned_blocks = 0;
+    for chunk in save.chunks.into_values() {
+        for block in chunk {
+            if registry.get(BlockType(block.block_type)).is_some() {
+                blocks.push(block);
+            } else {
+                pruned_blocks += 1;
+            }
+        }
+    }
+    // Neighbouring blocks written together compress better, and the file diffs cleanly.
+    blocks.sort_by_key(|block| {
+        let (x, y, z) = block.position;
+        (y, z, x)
+    });
+
+    let count = blocks.len();
+    write_world(path, save.generation + 1, blocks, compression_level)?;
+    for delta in &delta_paths {
+        fs::remove_file(delta)?;
+    }
+
+    Ok(OptimizeReport {
+        blocks: count,
+        pruned_blocks,
+        deltas: save.deltas,
+        size_before,
+        size_after: fs::metadata(path)?.len(),
+    })
+}
+
+/// Saves on `Action::SaveWorld`, and every `AUTOSAVE_INTERVAL` if something changed.
+fn save_world(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut world: ResMut<WorldFile>,
+    mut chunks: ResMut<Chunks>,
+) {
+    let requested = input_map.just_pressed(Action::SaveWorld, &keys, &mouse);
+    let autosave = world.autosave.tick(time.delta()).just_finished() && chunks.has_unsaved();
+
+    if !(requested || autosave) {
+        return;
+    }
+
+    let _span = info_span!("save_world").entered();
+
+    // Deltas apply on top of a full save, so the first save of a new world is a full one.
+    let compact = world.deltas + 1 >= DELTAS_PER_FULL_SAVE || fs::metadata(&world.path).is_err();
+
+    if requested || compact {
+        save_full(&mut world, &mut chunks);
+    } else {
+        save_delta(&mut world, &mut chunks);
+    }
+}
+
+/// Saves the placed blocks to a RON file, compressed with zstd, and loads them back on startup.
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldFile {
+            path: world_path(),
+            generation: 0,
+            deltas: 0,
+            compression_level: compression::level(),
+            autosave: Timer::from_seconds(AUTOSAVE_INTERVAL, true),
+        })
+        .add_startup_system(load_world)
+        .add_system(save_world);
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowFocused};
+use bevy::winit::{UpdateMode, WinitSettings};
+
+use crate::input_map::{Action, InputMap};
+
+/// Frame rate the app is throttled to in low power mode, when nothing happens.
+const LOW_POWER_FPS: f32 = 10.0;
+
+/// Throttles the app while the window is in the background or when low power mode is toggled on.
+///
+/// Frames are then only drawn on input or at `LOW_POWER_FPS`, with vsync on. Forced on at startup
+/// with `--low-power`.
+#[derive(Default)]
+pub struct LowPower {
+    pub forced: bool,
+    unfocused: bool,
+}
+
+impl LowPower {
+    pub fn active(&self) -> bool {
+        self.forced || self.unfocused
+    }
+}
+
+fn toggle_low_power(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut low_power: ResMut<LowPower>,
+) {
+    if input_map.just_pressed(Action::ToggleLowPower, &keys, &mouse) {
+        low_power.forced = !low_power.forced;
+        info!(
+            "Low power mode {}",
+            if low_power.forced { "on" } else { "off" }
+        );
+    }
+}
+
+fn track_focus(mut events: EventReader<WindowFocused>, mut low_power: ResMut<LowPower>) {
+    if let Some(event) = events.iter().last() {
+        low_power.unfocused = !event.focused;
+    }
+}
+
+fn apply_low_power(
+    low_power: Res<LowPower>,
+    mut winit: ResMut<WinitSettings>,
+    mut windows: ResMut<Windows>,
+) {
+    if !low_power.is_changed() {
+        return;
+    }
+
+    let (update_mode, present_mode) = if low_power.active() {
+        let update_mode = UpdateMode::ReactiveLowPower {
+            max_wait: Duration::from_secs_f32(1.0 / LOW_POWER_FPS),
+        };
+        (update_mode, PresentMode::AutoVsync)
+    } else {
+        (UpdateMode::Continuous, PresentMode::AutoNoVsync)
+    };
+
+    // Both modes are set, focus changes are handled by `LowPower` itself.
+    winit.focused_mode = update_mode;
+    winit.unfocused_mode = update_mode;
+
+    if let Some(window) = windows.get_primary_mut() {
+        if window.present_mode() != present_mode {
+            window.set_present_mode(present_mode);
+        }
+    }
+}
+
+/// Stops the editor from spinning the fans while it sits in the background.
+pub struct LowPowerPlugin;
+
+impl Plugin for LowPowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LowPower {
+            forced: std::env::args().any(|arg| arg == "--low-power"),
+            ..default()
+        })
+        .init_resource::<WinitSettings>()
+        .add_system(toggle_low_power)
+        .add_system(track_focus)
+        .add_system(apply_low_power.after(toggle_low_power).after(track_focus));
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::block::{BlockAssets, BlockMap, BlockRegistry, SelectedBlock};
+use crate::block_position_from_hit;
+use crate::box_select::ToolMode;
+use crate::voxel_raycast::PointedFace;
+
+/// Translucent cube showing where the next block would be placed.
+#[derive(Component)]
+struct GhostBlock;
+
+struct GhostMaterials {
+    valid: Handle<StandardMaterial>,
+    invalid: Handle<StandardMaterial>,
+}
+
+fn setup_ghost_block(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<BlockAssets>,
+) {
+    let ghost_materials = GhostMaterials {
+        valid: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 1.0, 1.0, 0.3),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        invalid: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 0.1, 0.1, 0.4),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    };
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: assets.cube.clone(),
+            material: ghost_materials.valid.clone(),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(GhostBlock);
+
+    commands.insert_resource(ghost_materials);
+}
+
+/// Snaps the ghost to the cell next to the pointed face, red if the selected block can't go there.
+fn update_ghost_block(
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    map: Res<BlockMap>,
+    ghost_materials: Res<GhostMaterials>,
+    mode: Res<ToolMode>,
+    pointed: Res<PointedFace>,
+    mut ghost: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut Handle<StandardMaterial>,
+        ),
+        With<GhostBlock>,
+    >,
+) {
+    for (mut transform, mut visibility, mut material) in &mut ghost {
+        let hit = match pointed.0.filter(|_| *mode == ToolMode::Build) {
+            Some(hit) => hit,
+            None => {
+                visibility.is_visible = false;
+                continue;
+            }
+        };
+
+        let cell = block_position_from_hit(hit.position, hit.normal);
+        let valid = registry.can_place(selected.0, cell, &map);
+
+        visibility.is_visible = true;
+        *transform = cell.into_transform();
+        *material = if valid {
+            ghost_materials.valid.clone()
+        } else {
+            ghost_materials.invalid.clone()
+        };
+    }
+}
+
+/// Preview of the block about to be placed at the cursor.
+pub struct PlacementPreviewPlugin;
+
+impl Plugin for PlacementPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_ghost_block)
+            .add_system(update_ghost_block);
+    }
+}
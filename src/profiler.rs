@@ -0,0 +1,86 @@
+use bevy::diagnostic::{Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use crate::input_map::{Action, InputMap};
+use crate::version::version;
+
+#[derive(Component)]
+struct ProfilerOverlay;
+
+fn toggle_profiler_overlay(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<ProfilerOverlay>>,
+) {
+    if !input_map.just_pressed(Action::ToggleProfiler, &keys, &mouse) {
+        return;
+    }
+
+    if !query.is_empty() {
+        for entity in &query {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 20.0,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(8.0),
+                    right: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ProfilerOverlay);
+}
+
+fn update_profiler_overlay(
+    diagnostics: Res<Diagnostics>,
+    mut query: Query<&mut Text, With<ProfilerOverlay>>,
+) {
+    let average = |id| diagnostics.get(id).and_then(|d| d.average());
+
+    let fps = average(FrameTimeDiagnosticsPlugin::FPS).unwrap_or_default();
+    let frame_time = if fps > 0.0 { 1000.0 / fps } else { 0.0 };
+    let entities = average(EntityCountDiagnosticsPlugin::ENTITY_COUNT).unwrap_or_default();
+
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "{:.0} FPS\n{:.2} ms/frame\n{:.0} entities\n{}",
+            fps,
+            frame_time,
+            entities,
+            version()
+        );
+    }
+}
+
+/// In-app performance overlay, toggled with F3.
+///
+/// For detailed traces, build with `--features tracy` and connect the Tracy profiler: every system,
+/// raycasting included, and the hot paths (flood fills, block spawning) show up as spans.
+pub struct ProfilerPlugin;
+
+impl Plugin for ProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_plugin(EntityCountDiagnosticsPlugin::default())
+            .add_system(toggle_profiler_overlay)
+            .add_system(update_profiler_overlay);
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::pbr::PointLightShadowMap;
+use bevy::prelude::*;
+use bevy::render::camera::Projection;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::BlockLight;
+use crate::power::LowPower;
+use crate::{cli_arg, MyRaycastSet};
+
+/// Seconds between two quality adjustments, so the frame rate can settle in between.
+const ADJUST_INTERVAL: f32 = 2.0;
+
+/// Render settings for one quality level, from lowest to highest.
+struct QualityLevel {
+    render_distance: f32,
+    shadows: bool,
+    shadow_map_size: usize,
+}
+
+const LEVELS: [QualityLevel; 3] = [
+    QualityLevel {
+        render_distance: 100.0,
+        shadows: false,
+        shadow_map_size: 256,
+    },
+    QualityLevel {
+        render_distance: 250.0,
+        shadows: true,
+        shadow_map_size: 512,
+    },
+    QualityLevel {
+        render_distance: 1000.0,
+        shadows: true,
+        shadow_map_size: 1024,
+    },
+];
+
+/// Lowers the render quality when the frame rate drops below the target, and raises it back when
+/// there is headroom.
+///
+/// Configured with `--target-fps <fps>`, disabled with `--no-adaptive-quality`.
+pub struct AdaptiveQuality {
+    pub enabled: bool,
+    pub target_fps: f64,
+    level: usize,
+    timer: Timer,
+}
+
+impl Default for AdaptiveQuality {
+    fn default() -> Self {
+        AdaptiveQuality {
+            enabled: true,
+            target_fps: 60.0,
+            level: LEVELS.len() - 1,
+            timer: Timer::from_seconds(ADJUST_INTERVAL, true),
+        }
+    }
+}
+
+fn adjust_quality(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    low_power: Res<LowPower>,
+    mut quality: ResMut<AdaptiveQuality>,
+) {
+    if !quality.enabled || !quality.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // The frame rate is throttled on purpose, not because of the load.
+    if low_power.active() {
+        return;
+    }
+
+    let fps = match diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|d| d.average())
+    {
+        Some(fps) => fps,
+        None => return,
+    };
+
+    // Wide margins so the level doesn't flip back and forth around the target.
+    if fps < quality.target_fps * 0.9 && quality.level > 0 {
+        quality.level -= 1;
+        info!(
+            "Lowering render quality to level {} ({:.0} FPS)",
+            quality.level, fps
+        );
+    } else if fps > quality.target_fps * 1.4 && quality.level < LEVELS.len() - 1 {
+        quality.level += 1;
+        info!(
+            "Raising render quality to level {} ({:.0} FPS)",
+            quality.level, fps
+        );
+    }
+}
+
+/// Applies the current level to the cameras and lights, including ones spawned later.
+fn apply_quality(
+    quality: Res<AdaptiveQuality>,
+    mut shadow_map: ResMut<PointLightShadowMap>,
+    mut cameras: Query<&mut Projection, With<RayCastSource<MyRaycastSet>>>,
+    new_cameras: Query<(), Added<RayCastSource<MyRaycastSet>>>,
+    mut lights: Query<&mut PointLight, Without<BlockLight>>,
+    new_lights: Query<(), (Added<PointLight>, Without<BlockLight>)>,
+) {
+    if !quality.is_changed() && new_cameras.is_empty() && new_lights.is_empty() {
+        return;
+    }
+
+    let level = &LEVELS[quality.level];
+
+    for mut projection in &mut cameras {
+        if let Projection::Perspective(perspective) = &mut *projection {
+            perspective.far = level.render_distance;
+        }
+    }
+
+    for mut light in &mut lights {
+        light.shadows_enabled = level.shadows;
+    }
+
+    if shadow_map.size != level.shadow_map_size {
+        shadow_map.size = level.shadow_map_size;
+    }
+}
+
+/// Reads the frame rate measured by `FrameTimeDiagnosticsPlugin`, added by `ProfilerPlugin`.
+pub struct AdaptiveQualityPlugin;
+
+impl Plugin for AdaptiveQualityPlugin {
+    fn build(&self, app: &mut App) {
+        let mut quality = AdaptiveQuality {
+            enabled: !std::env::args().any(|arg| arg == "--no-adaptive-quality"),
+            ..default()
+        };
+
+        if let Some(target_fps) = cli_arg("--target-fps").and_then(|fps| fps.parse().ok()) {
+            quality.target_fps = target_fps;
+        }
+
+        app.insert_resource(quality)
+            .add_system(adjust_quality)
+            .add_system(apply_quality.after(adjust_quality));
+    }
+}
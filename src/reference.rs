@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+use crate::cli_arg;
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+
+/// Width of a freshly loaded reference image, in cells.
+const DEFAULT_WIDTH: f32 = 16.0;
+/// Factor applied to the width each time the image is grown or shrunk.
+const SCALE_STEP: f32 = 1.1;
+/// Offset from the face the image is placed on, so it doesn't flicker against it.
+const SURFACE_OFFSET: f32 = 0.01;
+
+/// Translucent image to build over, like concept art or a floor plan.
+///
+/// Start the game with `--reference <path>`, the path being relative to the `assets` directory.
+/// The image is placed on the face under the cursor, scaled, and locked so it can't be moved by
+/// accident.
+pub struct ReferenceImage {
+    image: Handle<Image>,
+    width: f32,
+    aspect_ratio: Option<f32>,
+    pub locked: bool,
+}
+
+#[derive(Component)]
+struct ReferencePlane;
+
+fn setup_reference_image(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    reference: Res<ReferenceImage>,
+) {
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane { size: 1.0 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 1.0, 1.0, 0.5),
+                base_color_texture: Some(reference.image.clone()),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                // Seen from both sides when placed on a wall.
+                cull_mode: None,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, SURFACE_OFFSET, 0.0),
+            ..default()
+        })
+        .insert(ReferencePlane);
+}
+
+fn edit_reference_image(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut reference: ResMut<ReferenceImage>,
+    pointed: Res<PointedFace>,
+    mut plane: Query<&mut Transform, With<ReferencePlane>>,
+) {
+    if input_map.just_pressed(Action::LockReference, &keys, &mouse) {
+        reference.locked = !reference.locked;
+        info!(
+            "Reference image {}",
+            if reference.locked {
+                "locked"
+            } else {
+                "unlocked"
+            }
+        );
+    }
+
+    if reference.locked {
+        return;
+    }
+
+    if input_map.just_pressed(Action::GrowReference, &keys, &mouse) {
+        reference.width *= SCALE_STEP;
+    }
+    if input_map.just_pressed(Action::ShrinkReference, &keys, &mouse) {
+        reference.width /= SCALE_STEP;
+    }
+
+    if input_map.just_pressed(Action::PlaceReference, &keys, &mouse) {
+        if let Some(hit) = pointed.0 {
+            for mut transform in &mut plane {
+                transform.translation = hit.position + hit.normal * SURFACE_OFFSET;
+                // The plane faces up, turn it to face out of the pointed face.
+                transform.rotation = Quat::from_rotation_arc(Vec3::Y, hit.normal);
+            }
+        }
+    }
+}
+
+/// Sizes the plane to the image, once it is loaded and whenever it is scaled.
+fn resize_reference_plane(
+    images: Res<Assets<Image>>,
+    mut reference: ResMut<ReferenceImage>,
+    mut plane: Query<&mut Transform, With<ReferencePlane>>,
+) {
+    if reference.aspect_ratio.is_none() {
+        reference.aspect_ratio = images.get(&reference.image).map(|image| {
+            let size = image.size();
+            size.x / size.y
+        });
+    }
+
+    let aspect_ratio = match reference.aspect_ratio {
+        Some(aspect_ratio) => aspect_ratio,
+        None => return,
+    };
+
+    if !reference.is_changed() {
+        return;
+    }
+
+    for mut transform in &mut plane {
+        transform.scale = Vec3::new(reference.width, 1.0, reference.width / aspect_ratio);
+    }
+}
+
+/// Translucent reference image placed in the world to build over.
+pub struct ReferencePlugin;
+
+impl Plugin for ReferencePlugin {
+    fn build(&self, app: &mut App) {
+        let path = match cli_arg("--reference") {
+            Some(path) => path,
+            None => return,
+        };
+
+        let image = app.world.resource::<AssetServer>().load(path.as_str());
+
+        app.insert_resource(ReferenceImage {
+            image,
+            width: DEFAULT_WIDTH,
+            aspect_ratio: None,
+            locked: false,
+        })
+        .add_startup_system(setup_reference_image)
+        .add_system(edit_reference_image)
+        .add_system(resize_reference_plane.after(edit_reference_image));
+    }
+}
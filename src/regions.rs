@@ -0,0 +1,352 @@
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::FocusCamera;
+use crate::input_map::{Action, InputMap};
+use crate::persistence::world_path;
+use crate::selection::Selected;
+use crate::{BlockPosition, MyRaycastSet};
+
+/// Labels are only shown when the camera is further than this from the region center.
+const LABEL_MIN_DISTANCE: f32 = 15.0;
+
+/// A named box of cells, bounds included.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub min: (i64, i64, i64),
+    pub max: (i64, i64, i64),
+}
+
+impl Region {
+    pub fn center(&self) -> Vec3 {
+        Vec3::new(
+            (self.min.0 + self.max.0) as f32 / 2.0,
+            (self.min.1 + self.max.1) as f32 / 2.0,
+            (self.min.2 + self.max.2) as f32 / 2.0,
+        )
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Regions(pub Vec<Region>);
+
+/// Regions are saved next to the world they were named in, as `<world path>.regions`.
+fn regions_path() -> String {
+    format!("{}.regions", world_path())
+}
+
+impl Regions {
+    fn load() -> Self {
+        fs::read_to_string(regions_path())
+            .ok()
+            .and_then(|content| ron::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|content| fs::write(regions_path(), content).map_err(|err| err.to_string()));
+
+        if let Err(err) = result {
+            error!("Could not save regions: {}", err);
+        }
+    }
+}
+
+/// Region waiting for its name to be typed.
+struct NamingRegion {
+    min: (i64, i64, i64),
+    max: (i64, i64, i64),
+    name: String,
+}
+
+#[derive(Component)]
+struct NamingPrompt;
+
+#[derive(Component)]
+struct RegionLabel(usize);
+
+#[derive(Component)]
+struct Legend;
+
+#[derive(Component)]
+struct LegendButton(usize);
+
+fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/DejaVuSans.ttf"),
+        font_size,
+        color: Color::WHITE,
+    }
+}
+
+/// Starts naming the bounding box of the selected blocks.
+fn start_naming_region(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut input_map: ResMut<InputMap>,
+    asset_server: Res<AssetServer>,
+    naming: Option<Res<NamingRegion>>,
+    selected: Query<&BlockPosition, With<Selected>>,
+) {
+    if naming.is_some() || !input_map.just_pressed(Action::NameRegion, &keys, &mouse) {
+        return;
+    }
+
+    let mut cells = selected.iter();
+    let first = match cells.next() {
+        Some(first) => (first.x, first.y, first.z),
+        None => {
+            info!("Select blocks before naming a region");
+            return;
+        }
+    };
+    let (min, max) = cells.fold((first, first), |(min, max), p| {
+        (
+            (min.0.min(p.x), min.1.min(p.y), min.2.min(p.z)),
+            (max.0.max(p.x), max.1.max(p.y), max.2.max(p.z)),
+        )
+    });
+
+    commands.insert_resource(NamingRegion {
+        min,
+        max,
+        name: String::new(),
+    });
+    input_map.suspended = true;
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section("Region name: ", text_style(&asset_server, 28.0)).with_style(
+                Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        bottom: Val::Px(80.0),
+                        left: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ),
+        )
+        .insert(NamingPrompt);
+}
+
+/// Types the region name, Enter to confirm and Escape to cancel.
+fn type_region_name(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut input_map: ResMut<InputMap>,
+    mut regions: ResMut<Regions>,
+    naming: Option<ResMut<NamingRegion>>,
+    mut prompt: Query<(Entity, &mut Text), With<NamingPrompt>>,
+) {
+    // Always drained, so the key that started naming isn't typed into the name.
+    let typed: Vec<char> = characters.iter().map(|event| event.char).collect();
+
+    let mut naming = match naming {
+        Some(naming) => naming,
+        None => return,
+    };
+
+    let done = keys.just_pressed(KeyCode::Return);
+    let cancelled = keys.just_pressed(KeyCode::Escape);
+
+    for c in typed {
+        match c {
+            '\u{8}' => {
+                naming.name.pop();
+            }
+            c if !c.is_control() => naming.name.push(c),
+            _ => {}
+        }
+    }
+
+    if done || cancelled {
+        if done && !naming.name.trim().is_empty() {
+            regions.0.push(Region {
+                name: naming.name.trim().to_string(),
+                min: naming.min,
+                max: naming.max,
+            });
+            regions.save();
+        }
+
+        commands.remove_resource::<NamingRegion>();
+        input_map.suspended = false;
+        for (entity, _) in &prompt {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    for (_, mut text) in &mut prompt {
+        text.sections[0].value = format!("Region name: {}", naming.name);
+    }
+}
+
+fn spawn_region_labels(
+    mut commands: Commands,
+    regions: Res<Regions>,
+    asset_server: Res<AssetServer>,
+    labels: Query<Entity, With<RegionLabel>>,
+) {
+    if !regions.is_changed() {
+        return;
+    }
+
+    for entity in &labels {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (i, region) in regions.0.iter().enumerate() {
+        commands
+            .spawn_bundle(
+                TextBundle::from_section(region.name.clone(), text_style(&asset_server, 22.0))
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    }),
+            )
+            .insert(RegionLabel(i));
+    }
+}
+
+/// Floats the labels over their regions when the camera is far enough.
+fn update_region_labels(
+    regions: Res<Regions>,
+    camera: Query<(&Camera, &GlobalTransform), With<RayCastSource<MyRaycastSet>>>,
+    mut labels: Query<(&RegionLabel, &mut Style, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    for (label, mut style, mut visibility) in &mut labels {
+        let region = match regions.0.get(label.0) {
+            Some(region) => region,
+            None => continue,
+        };
+        // Above the region rather than in its middle.
+        let height = (region.max.1 - region.min.1) as f32;
+        let anchor = region.center() + Vec3::Y * (height / 2.0 + 1.0);
+        let far = camera_transform.translation().distance(anchor) > LABEL_MIN_DISTANCE;
+
+        match camera.world_to_viewport(camera_transform, anchor) {
+            Some(position) if far => {
+                visibility.is_visible = true;
+                style.position = UiRect {
+                    left: Val::Px(position.x),
+                    bottom: Val::Px(position.y),
+                    ..default()
+                };
+            }
+            _ => visibility.is_visible = false,
+        }
+    }
+}
+
+fn toggle_legend(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    regions: Res<Regions>,
+    asset_server: Res<AssetServer>,
+    legend: Query<Entity, With<Legend>>,
+) {
+    let toggled = input_map.just_pressed(Action::ToggleLegend, &keys, &mouse);
+    let open = !legend.is_empty();
+
+    // Rebuild an open legend when regions change.
+    if !(toggled || (open && regions.is_changed())) {
+        return;
+    }
+
+    for entity in &legend {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if toggled && open {
+        return;
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(8.0),
+                    right: Val::Px(8.0),
+                    ..default()
+                },
+                // UI is laid out from the bottom, so reverse to list from the top.
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..default()
+        })
+        .insert(Legend)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                "Regions",
+                text_style(&asset_server, 26.0),
+            ));
+
+            for (i, region) in regions.0.iter().enumerate() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .insert(LegendButton(i))
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle::from_section(
+                            region.name.clone(),
+                            text_style(&asset_server, 22.0),
+                        ));
+                    });
+            }
+        });
+}
+
+/// Clicking a region in the legend moves the camera to it.
+fn teleport_to_region(
+    regions: Res<Regions>,
+    mut focus: EventWriter<FocusCamera>,
+    query: Query<(&Interaction, &LegendButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &query {
+        if *interaction == Interaction::Clicked {
+            if let Some(region) = regions.0.get(button.0) {
+                focus.send(FocusCamera(region.center()));
+            }
+        }
+    }
+}
+
+/// Named regions of the world, shown as floating labels and listed in a legend.
+pub struct RegionsPlugin;
+
+impl Plugin for RegionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Regions::load())
+            .add_system(start_naming_region)
+            .add_system(type_region_name.after(start_naming_region))
+            .add_system(spawn_region_labels.after(type_region_name))
+            .add_system(update_region_labels.after(spawn_region_labels))
+            .add_system(toggle_legend.after(type_region_name))
+            .add_system(teleport_to_region);
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::{BlockMap, BlockRegistry, SelectedBlock};
+use crate::box_select::ToolMode;
+use crate::history::{BlockEdit, EditBlocks};
+use crate::input_map::{Action, InputMap};
+use crate::voxel_raycast::PointedFace;
+use crate::{block_position_from_hit, BlockPosition, MyRaycastSet};
+
+/// Delay between two blocks placed while holding `Action::PlaceBlock`, in seconds.
+const REPEAT_INTERVAL: f32 = 0.15;
+
+/// Whether holding `Action::PlaceBlock` keeps extending blocks out of the clicked face.
+#[derive(Default)]
+struct RepeatPlacement {
+    enabled: bool,
+}
+
+/// The row of blocks currently being extended.
+struct Extrusion {
+    last: BlockPosition,
+    direction: (i64, i64, i64),
+    timer: Timer,
+}
+
+/// Unit step along the main axis of the face normal.
+pub fn face_direction(normal: Vec3) -> (i64, i64, i64) {
+    let abs = normal.abs();
+    let sign = |v: f32| if v > 0.0 { 1 } else { -1 };
+
+    if abs.x >= abs.y && abs.x >= abs.z {
+        (sign(normal.x), 0, 0)
+    } else if abs.y >= abs.z {
+        (0, sign(normal.y), 0)
+    } else {
+        (0, 0, sign(normal.z))
+    }
+}
+
+fn toggle_repeat_placement(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut repeat: ResMut<RepeatPlacement>,
+) {
+    if input_map.just_pressed(Action::ToggleRepeatPlacement, &keys, &mouse) {
+        repeat.enabled = !repeat.enabled;
+        info!(
+            "Repeat placement {}",
+            if repeat.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+fn repeat_placement(
+    mut edits: EventWriter<EditBlocks>,
+    registry: Res<BlockRegistry>,
+    selected: Res<SelectedBlock>,
+    map: Res<BlockMap>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    repeat: Res<RepeatPlacement>,
+    mode: Res<ToolMode>,
+    mut extrusion: Local<Option<Extrusion>>,
+    pointed: Res<PointedFace>,
+    player: Query<Entity, With<RayCastSource<MyRaycastSet>>>,
+) {
+    if !repeat.enabled
+        || *mode != ToolMode::Build
+        || !input_map.pressed(Action::PlaceBlock, &keys, &mouse)
+    {
+        *extrusion = None;
+        return;
+    }
+
+    // The first block is placed by `new_cube_from_raycast`, only remember where the row starts.
+    if input_map.just_pressed(Action::PlaceBlock, &keys, &mouse) {
+        *extrusion = pointed.0.map(|hit| Extrusion {
+            last: block_position_from_hit(hit.position, hit.normal),
+            direction: face_direction(hit.normal),
+            timer: Timer::from_seconds(REPEAT_INTERVAL, true),
+        });
+        return;
+    }
+
+    let row = match extrusion.as_mut() {
+        Some(row) => row,
+        None => return,
+    };
+
+    if !row.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let (dx, dy, dz) = row.direction;
+    let next = BlockPosition {
+        x: row.last.x + dx,
+        y: row.last.y + dy,
+        z: row.last.z + dz,
+    };
+
+    if !registry.can_place(selected.0, next, &map) {
+        // Stop at the first obstacle, until the button is pressed again.
+        *extrusion = None;
+        return;
+    }
+
+    edits.send(EditBlocks {
+        edits: vec![BlockEdit::Place {
+            position: next,
+            block_type: selected.0,
+        }],
+        source: player.get_single().ok(),
+    });
+    row.last = next;
+}
+
+/// Holding the place button extends a row of blocks out of the clicked face, toggled with T.
+pub struct RepeatPlacementPlugin;
+
+impl Plugin for RepeatPlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RepeatPlacement>()
+            .add_system(toggle_repeat_placement)
+            .add_system(repeat_placement);
+    }
+}
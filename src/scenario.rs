@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::cli_arg;
+use crate::compression;
+use crate::history::{BlockPlaced, BlockRemoved};
+
+/// A challenge loaded from a RON file: a target shape to build and the constraints to build it under.
+///
+/// Start the game with `--scenario <path>` to play one.
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Cells that must be filled with a block for the scenario to be completed.
+    pub target: HashSet<(i64, i64, i64)>,
+    /// Maximum number of blocks placed by the player at once.
+    #[serde(default)]
+    pub block_budget: Option<usize>,
+    /// Time limit in seconds.
+    #[serde(default)]
+    pub time_limit: Option<f32>,
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = compression::read_to_string(path)?;
+        let scenario = ron::from_str(&content)?;
+        Ok(scenario)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Completed,
+    OutOfBlocks,
+    OutOfTime,
+}
+
+#[derive(Default)]
+struct ScenarioProgress {
+    elapsed: f32,
+    /// Cells filled by the player, leaving out loaded and generated blocks.
+    cells: HashSet<(i64, i64, i64)>,
+    placed: usize,
+    matched: usize,
+    outcome: Option<Outcome>,
+}
+
+#[derive(Component)]
+struct ScenarioText;
+
+fn update_scenario_progress(
+    time: Res<Time>,
+    scenario: Res<Scenario>,
+    mut progress: ResMut<ScenarioProgress>,
+    mut placed: EventReader<BlockPlaced>,
+    mut removed: EventReader<BlockRemoved>,
+) {
+    if progress.outcome.is_some() {
+        return;
+    }
+
+    progress.elapsed += time.delta_seconds();
+
+    for event in placed.iter() {
+        let cell = (event.position.x, event.position.y, event.position.z);
+        if progress.cells.insert(cell) && scenario.target.contains(&cell) {
+            progress.matched += 1;
+        }
+    }
+    for event in removed.iter() {
+        let cell = (event.position.x, event.position.y, event.position.z);
+        if progress.cells.remove(&cell) && scenario.target.contains(&cell) {
+            progress.matched -= 1;
+        }
+    }
+    progress.placed = progress.cells.len();
+
+    let outcome =
+        if progress.matched == scenario.target.len() && progress.placed == progress.matched {
+            Some(Outcome::Completed)
+        } else if scenario
+            .block_budget
+            .map_or(false, |budget| progress.placed > budget)
+        {
+            Some(Outcome::OutOfBlocks)
+        } else if scenario
+            .time_limit
+            .map_or(false, |limit| progress.elapsed > limit)
+        {
+            Some(Outcome::OutOfTime)
+        } else {
+            None
+        };
+
+    if let Some(outcome) = outcome {
+        match outcome {
+            Outcome::Completed => info!(
+                "Scenario \"{}\" completed in {:.1}s",
+                scenario.name, progress.elapsed
+            ),
+            Outcome::OutOfBlocks => info!("Scenario \"{}\" failed: out of blocks", scenario.name),
+            Outcome::OutOfTime => info!("Scenario \"{}\" failed: out of time", scenario.name),
+        }
+        progress.outcome = Some(outcome);
+    }
+}
+
+fn spawn_scenario_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(8.0),
+                    left: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ScenarioText);
+}
+
+fn update_scenario_text(
+    scenario: Res<Scenario>,
+    progress: Res<ScenarioProgress>,
+    mut query: Query<&mut Text, With<ScenarioText>>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![
+        scenario.name.clone(),
+        format!("Target: {}/{}", progress.matched, scenario.target.len()),
+    ];
+
+    match scenario.block_budget {
+        Some(budget) => lines.push(format!("Blocks: {}/{}", progress.placed, budget)),
+        None => lines.push(format!("Blocks: {}", progress.placed)),
+    }
+
+    match scenario.time_limit {
+        Some(limit) => lines.push(format!("Time: {:.0}s", (limit - progress.elapsed).max(0.0))),
+        None => lines.push(format!("Time: {:.0}s", progress.elapsed)),
+    }
+
+    match progress.outcome {
+        Some(Outcome::Completed) => lines.push("Completed!".to_string()),
+        Some(Outcome::OutOfBlocks) => lines.push("Failed: out of blocks".to_string()),
+        Some(Outcome::OutOfTime) => lines.push("Failed: out of time".to_string()),
+        None => {}
+    }
+
+    for mut text in &mut query {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Puzzle mode on top of the sandbox, enabled when a scenario file is given on the command line.
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        let path = match cli_arg("--scenario") {
+            Some(path) => path,
+            None => return,
+        };
+
+        let scenario = match Scenario::load(&path) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                error!("Could not load scenario {}: {}", path, err);
+                return;
+            }
+        };
+
+        app.insert_resource(scenario)
+            .init_resource::<ScenarioProgress>()
+            .add_startup_system(spawn_scenario_text)
+            .add_system(update_scenario_progress)
+            .add_system(update_scenario_text.after(update_scenario_progress));
+    }
+}
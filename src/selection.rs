@@ -0,0 +1,145 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::block::BlockMap;
+use crate::box_select::ToolMode;
+use crate::gesture::Gesture;
+use crate::voxel_raycast::PointedFace;
+use crate::{pointed_block, BlockPosition, FloorTile};
+
+/// Tags a selected block, holding the entity of its highlight.
+#[derive(Component)]
+pub struct Selected(Entity);
+
+struct SelectionAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_selection(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(SelectionAssets {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 0.9, 0.2, 0.4),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+fn select(commands: &mut Commands, assets: &SelectionAssets, entity: Entity) {
+    let highlight = commands
+        .spawn_bundle(PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.material.clone(),
+            // Slightly bigger than the block so it wraps around it.
+            transform: Transform::from_scale(Vec3::splat(1.05)),
+            ..default()
+        })
+        .id();
+
+    commands
+        .entity(entity)
+        .insert(Selected(highlight))
+        .add_child(highlight);
+}
+
+fn deselect(commands: &mut Commands, entity: Entity, selected: &Selected) {
+    commands.entity(selected.0).despawn_recursive();
+    commands.entity(entity).remove::<Selected>();
+}
+
+/// Blocks connected to `start` through their faces, `is_block` leaving out e.g. floor tiles.
+fn connected_blocks(
+    start: BlockPosition,
+    map: &BlockMap,
+    is_block: impl Fn(Entity) -> bool,
+) -> Vec<Entity> {
+    let _span = info_span!("connected_blocks").entered();
+
+    let mut visited = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut connected = Vec::new();
+
+    while let Some(position) = queue.pop_front() {
+        connected.extend(map.get(position));
+
+        for (neighbour, entity) in map.neighbours(position) {
+            if is_block(entity) && visited.insert(neighbour) {
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    connected
+}
+
+/// Middle double-click selects a whole structure, Shift+click adds a block and Ctrl+click removes
+/// one.
+fn apply_selection_gestures(
+    mut commands: Commands,
+    mut gestures: EventReader<Gesture>,
+    assets: Res<SelectionAssets>,
+    map: Res<BlockMap>,
+    mode: Res<ToolMode>,
+    pointed: Res<PointedFace>,
+    blocks: Query<(Entity, &BlockPosition, Option<&Selected>), Without<FloorTile>>,
+) {
+    for gesture in gestures.iter() {
+        // The other tools use the same clicks.
+        if *mode != ToolMode::Build {
+            continue;
+        }
+
+        let target = match gesture {
+            Gesture::DoubleClick(target) => target.and_then(|position| map.get(position)),
+            _ => pointed_block(&pointed, &map).map(|(entity, _)| entity),
+        };
+
+        let (target, position, selected) = match target.and_then(|e| blocks.get(e).ok()) {
+            Some(block) => block,
+            None => continue,
+        };
+
+        match gesture {
+            Gesture::DoubleClick(_) => {
+                for (entity, _, selected) in &blocks {
+                    if let Some(selected) = selected {
+                        deselect(&mut commands, entity, selected);
+                    }
+                }
+
+                let is_block = |entity| blocks.contains(entity);
+                for entity in connected_blocks(*position, &map, is_block) {
+                    select(&mut commands, &assets, entity);
+                }
+            }
+            Gesture::AddClick => {
+                if selected.is_none() {
+                    select(&mut commands, &assets, target);
+                }
+            }
+            Gesture::RemoveClick => {
+                if let Some(selected) = selected {
+                    deselect(&mut commands, target, selected);
+                }
+            }
+        }
+    }
+}
+
+/// Selection of blocks, driven by gestures.
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_selection)
+            .add_system(apply_selection_gestures);
+    }
+}
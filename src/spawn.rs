@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use bevy_mod_raycast::RayCastSource;
+
+use crate::block::BlockMap;
+use crate::camera::FocusCamera;
+use crate::input_map::{Action, InputMap};
+use crate::regions::Regions;
+use crate::voxel_raycast::PointedFace;
+use crate::{cli_arg, pointed_block, BlockPosition, MyRaycastSet};
+
+/// Number of named regions listed in the compass as waypoints, nearest first.
+const MAX_WAYPOINTS: usize = 3;
+/// Arrows pointing to a direction relative to the camera heading, every 45°, clockwise.
+const ARROWS: [&str; 8] = ["↑", "↗", "→", "↘", "↓", "↙", "←", "↖"];
+const CARDINALS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// Cell the camera returns to with `Action::ReturnToSpawn`.
+///
+/// Set with `--spawn <x>,<y>,<z>` or `Action::SetSpawn` on a block.
+pub struct SpawnPoint(pub BlockPosition);
+
+impl SpawnPoint {
+    fn from_arg(arg: &str) -> Option<Self> {
+        let mut coordinates = arg.split(',').map(|c| c.trim().parse().ok());
+        let position = BlockPosition {
+            x: coordinates.next()??,
+            y: coordinates.next()??,
+            z: coordinates.next()??,
+        };
+        Some(SpawnPoint(position))
+    }
+}
+
+impl Default for SpawnPoint {
+    fn default() -> Self {
+        SpawnPoint(BlockPosition { x: 0, y: 0, z: 0 })
+    }
+}
+
+#[derive(Component)]
+struct SpawnMarker;
+
+#[derive(Component)]
+struct Compass;
+
+/// Bearing from `from` to `to` on the ground, in degrees clockwise from north (-Z).
+fn bearing(from: Vec3, to: Vec3) -> f32 {
+    let direction = to - from;
+    direction
+        .x
+        .atan2(-direction.z)
+        .to_degrees()
+        .rem_euclid(360.0)
+}
+
+/// Index in `ARROWS` or `CARDINALS` of the eighth of a turn the angle is in.
+fn octant(degrees: f32) -> usize {
+    (degrees.rem_euclid(360.0) / 45.0).round() as usize % 8
+}
+
+fn setup_spawn(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    // A beacon tall enough to be seen from afar.
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.2, 4.0, 0.2))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 0.85, 0.2, 0.6),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            ..default()
+        })
+        .insert(SpawnMarker);
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(8.0),
+                    left: Val::Percent(40.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(Compass);
+}
+
+fn set_spawn_point(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    mut spawn: ResMut<SpawnPoint>,
+    mut focus: EventWriter<FocusCamera>,
+    map: Res<BlockMap>,
+    pointed: Res<PointedFace>,
+) {
+    if input_map.just_pressed(Action::SetSpawn, &keys, &mouse) {
+        if let Some((_, position)) = pointed_block(&pointed, &map) {
+            spawn.0 = position;
+        }
+    }
+
+    if input_map.just_pressed(Action::ReturnToSpawn, &keys, &mouse) {
+        focus.send(FocusCamera(spawn.0.into_transform().translation));
+    }
+}
+
+fn update_spawn_marker(
+    spawn: Res<SpawnPoint>,
+    mut query: Query<&mut Transform, With<SpawnMarker>>,
+) {
+    if !spawn.is_changed() {
+        return;
+    }
+
+    for mut transform in &mut query {
+        // Standing on top of the spawn cell.
+        *transform = spawn.0.into_transform();
+        transform.translation.y += 2.5;
+    }
+}
+
+/// Shows the camera heading, and the direction and distance to the spawn point and waypoints.
+fn update_compass(
+    spawn: Res<SpawnPoint>,
+    regions: Res<Regions>,
+    camera: Query<&GlobalTransform, With<RayCastSource<MyRaycastSet>>>,
+    mut compass: Query<&mut Text, With<Compass>>,
+) {
+    let camera = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let position = camera.translation();
+    let heading = bearing(Vec3::ZERO, camera.forward());
+
+    let pointer = |name: &str, target: Vec3| {
+        let distance = Vec2::new(target.x - position.x, target.z - position.z).length();
+        let arrow = ARROWS[octant(bearing(position, target) - heading)];
+        format!("{} {} {:.0} m", name, arrow, distance)
+    };
+
+    let mut waypoints: Vec<(f32, String)> = regions
+        .0
+        .iter()
+        .map(|region| {
+            let center = region.center();
+            (center.distance(position), pointer(&region.name, center))
+        })
+        .collect();
+    waypoints.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut lines = vec![
+        format!("{} {:.0}°", CARDINALS[octant(heading)], heading),
+        pointer("Spawn", spawn.0.into_transform().translation),
+    ];
+    lines.extend(
+        waypoints
+            .into_iter()
+            .take(MAX_WAYPOINTS)
+            .map(|(_, line)| line),
+    );
+
+    for mut text in &mut compass {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Spawn point with a marker and a way back to it, and a compass pointing to it.
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        let spawn = match cli_arg("--spawn") {
+            Some(arg) => SpawnPoint::from_arg(&arg).unwrap_or_else(|| {
+                error!("Invalid spawn point {}, expected <x>,<y>,<z>", arg);
+                SpawnPoint::default()
+            }),
+            None => SpawnPoint::default(),
+        };
+
+        app.insert_resource(spawn)
+            .add_startup_system(setup_spawn)
+            .add_system(set_spawn_point)
+            .add_system(update_spawn_marker.after(set_spawn_point))
+            .add_system(update_compass.after(set_spawn_point));
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::history::{BlockPlaced, BlockRemoved};
+use crate::input_map::{Action, InputMap};
+
+const LEADERBOARD_PATH: &str = "leaderboard.ron";
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize)]
+struct LeaderboardEntry {
+    /// Seconds since the UNIX epoch at which the run finished.
+    finished_at: u64,
+    seconds: f32,
+    blocks: usize,
+}
+
+impl LeaderboardEntry {
+    fn blocks_per_minute(&self) -> f32 {
+        if self.seconds > 0.0 {
+            self.blocks as f32 * 60.0 / self.seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Best runs, sorted by blocks per minute.
+#[derive(Default, Serialize, Deserialize)]
+struct Leaderboard(Vec<LeaderboardEntry>);
+
+impl Leaderboard {
+    fn load() -> Self {
+        fs::read_to_string(LEADERBOARD_PATH)
+            .ok()
+            .and_then(|content| ron::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|content| {
+                fs::write(LEADERBOARD_PATH, content).map_err(|err| err.to_string())
+            });
+
+        if let Err(err) = result {
+            error!("Could not save leaderboard: {}", err);
+        }
+    }
+
+    /// Inserts the entry and returns its rank, starting at 1, if it made it into the leaderboard.
+    fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let rank = self
+            .0
+            .iter()
+            .position(|other| other.blocks_per_minute() < entry.blocks_per_minute())
+            .unwrap_or(self.0.len());
+
+        self.0.insert(rank, entry);
+        self.0.truncate(LEADERBOARD_SIZE);
+
+        (rank < LEADERBOARD_SIZE).then(|| rank + 1)
+    }
+}
+
+#[derive(Default, PartialEq, Eq)]
+enum RunState {
+    /// Waiting for the first block to start the timer.
+    #[default]
+    Ready,
+    Running,
+    Finished,
+}
+
+#[derive(Default)]
+struct SpeedBuildRun {
+    state: RunState,
+    seconds: f32,
+    blocks: usize,
+}
+
+#[derive(Component)]
+struct SpeedBuildText;
+
+#[derive(Component)]
+struct SummaryScreen;
+
+fn spawn_speed_build_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(8.0),
+                    right: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(SpeedBuildText);
+}
+
+fn update_speed_build_run(
+    time: Res<Time>,
+    mut run: ResMut<SpeedBuildRun>,
+    mut placed: EventReader<BlockPlaced>,
+    mut removed: EventReader<BlockRemoved>,
+) {
+    // Only the player's placements count, not undo, redo or imports. Repainting a block, e.g. with
+    // the bucket, removes it and places it back, which doesn't add to the build.
+    let repainted: HashSet<_> = removed
+        .iter()
+        .filter(|event| event.source.is_some())
+        .map(|event| event.position)
+        .collect();
+    let placed = placed
+        .iter()
+        .filter(|event| event.source.is_some() && !repainted.contains(&event.position))
+        .count();
+
+    if run.state == RunState::Ready && placed > 0 {
+        run.state = RunState::Running;
+    }
+
+    if run.state == RunState::Running {
+        run.seconds += time.delta_seconds();
+        run.blocks += placed;
+    }
+}
+
+fn update_speed_build_text(
+    run: Res<SpeedBuildRun>,
+    input_map: Res<InputMap>,
+    mut query: Query<&mut Text, With<SpeedBuildText>>,
+) {
+    if !run.is_changed() {
+        return;
+    }
+
+    let finish = match input_map.binding(Action::FinishSpeedBuild) {
+        Some(binding) => binding.to_string(),
+        None => "the finish binding".to_string(),
+    };
+
+    let value = match run.state {
+        RunState::Ready => "Speed build: place a block to start".to_string(),
+        _ => format!(
+            "Time: {:.1}s\nBlocks: {}\nPress {} to finish",
+            run.seconds, run.blocks, finish
+        ),
+    };
+
+    for mut text in &mut query {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// Ends the run and shows the summary; pressing again dismisses it and starts a new run.
+fn finish_speed_build_run(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    asset_server: Res<AssetServer>,
+    mut run: ResMut<SpeedBuildRun>,
+    summary: Query<Entity, With<SummaryScreen>>,
+) {
+    if !input_map.just_pressed(Action::FinishSpeedBuild, &keys, &mouse) {
+        return;
+    }
+
+    match run.state {
+        RunState::Ready => {}
+        RunState::Running => {
+            run.state = RunState::Finished;
+
+            let entry = LeaderboardEntry {
+                finished_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+                seconds: run.seconds,
+                blocks: run.blocks,
+            };
+            let blocks_per_minute = entry.blocks_per_minute();
+
+            let mut leaderboard = Leaderboard::load();
+            let rank = leaderboard.insert(entry);
+            leaderboard.save();
+
+            let mut lines = vec![
+                "Run finished".to_string(),
+                format!("Time: {:.1}s", run.seconds),
+                format!("Blocks: {}", run.blocks),
+                format!("Blocks/minute: {:.1}", blocks_per_minute),
+                match rank {
+                    Some(rank) => format!("Leaderboard rank: #{}", rank),
+                    None => "Not in the leaderboard".to_string(),
+                },
+                String::new(),
+                "Best runs".to_string(),
+            ];
+            for (i, entry) in leaderboard.0.iter().enumerate() {
+                lines.push(format!(
+                    "{}. {:.1} blocks/min ({} blocks in {:.1}s)",
+                    i + 1,
+                    entry.blocks_per_minute(),
+                    entry.blocks,
+                    entry.seconds
+                ));
+            }
+            lines.push(String::new());
+            lines.push("Press again to start a new run".to_string());
+
+            commands
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        position_type: PositionType::Absolute,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                    ..default()
+                })
+                .insert(SummaryScreen)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle::from_section(
+                        lines.join("\n"),
+                        TextStyle {
+                            font: asset_server.load("fonts/DejaVuSans.ttf"),
+                            font_size: 28.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        }
+        RunState::Finished => {
+            for entity in &summary {
+                commands.entity(entity).despawn_recursive();
+            }
+            *run = SpeedBuildRun::default();
+        }
+    }
+}
+
+/// Times how fast blocks are placed, enabled with `--speed-build`.
+///
+/// The timer starts with the first placed block and the run ends with `Action::FinishSpeedBuild`.
+/// Results are kept in a local leaderboard file.
+pub struct SpeedBuildPlugin;
+
+impl Plugin for SpeedBuildPlugin {
+    fn build(&self, app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--speed-build") {
+            return;
+        }
+
+        app.init_resource::<SpeedBuildRun>()
+            .add_startup_system(spawn_speed_build_text)
+            .add_system(finish_speed_build_run)
+            .add_system(update_speed_build_run.after(finish_speed_build_run))
+            .add_system(update_speed_build_text.after(update_speed_build_run));
+    }
+}
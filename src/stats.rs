@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::chunk::{ChunkPosition, CHUNK_SIZE};
+use crate::input_map::{Action, InputMap};
+use crate::{cli_arg, BlockPosition};
+
+/// Written by `Action::ExportStats`. A `.csv` extension picks CSV instead of JSON.
+const DEFAULT_STATS_PATH: &str = "stats.json";
+
+#[derive(Serialize)]
+struct ChunkOccupancy {
+    chunk: (i64, i64, i64),
+    blocks: usize,
+    /// Share of the chunk filled, from 0 to 1.
+    occupancy: f32,
+}
+
+/// Numbers about the placed blocks, for graphing a build outside of the game.
+#[derive(Serialize)]
+struct WorldStats {
+    total_blocks: usize,
+    /// Blocks of each type, by name.
+    block_counts: BTreeMap<String, usize>,
+    /// Blocks on each layer, by height.
+    height_histogram: BTreeMap<i64, usize>,
+    /// Chunks holding at least one block.
+    chunks: Vec<ChunkOccupancy>,
+    /// Blocks in the largest group connected through faces.
+    largest_structure: usize,
+}
+
+impl WorldStats {
+    fn compute(registry: &BlockRegistry, blocks: &[(BlockPosition, BlockType)]) -> Self {
+        let mut block_counts = BTreeMap::new();
+        let mut height_histogram = BTreeMap::new();
+        let mut chunk_counts: BTreeMap<(i64, i64, i64), usize> = BTreeMap::new();
+
+        for (position, block_type) in blocks {
+            let name = registry.get(*block_type).map_or_else(
+                || format!("unknown {}", block_type.0),
+                |definition| definition.name.to_string(),
+            );
+            *block_counts.entry(name).or_default() += 1;
+            *height_histogram.entry(position.y).or_default() += 1;
+
+            let (chunk, _) = ChunkPosition::of(*position);
+            *chunk_counts.entry((chunk.x, chunk.y, chunk.z)).or_default() += 1;
+        }
+
+        let volume = CHUNK_SIZE.pow(3) as f32;
+        let chunks = chunk_counts
+            .into_iter()
+            .map(|(chunk, blocks)| ChunkOccupancy {
+                chunk,
+                blocks,
+                occupancy: blocks as f32 / volume,
+            })
+            .collect();
+
+        WorldStats {
+            total_blocks: blocks.len(),
+            block_counts,
+            height_histogram,
+            chunks,
+            largest_structure: largest_structure(blocks.iter().map(|(position, _)| *position)),
+        }
+    }
+
+    /// One `section,key,value` row per number, so every section fits in the same table.
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("section,key,value\n");
+        let _ = writeln!(csv, "total_blocks,,{}", self.total_blocks);
+        let _ = writeln!(csv, "largest_structure,,{}", self.largest_structure);
+        for (name, count) in &self.block_counts {
+            let _ = writeln!(csv, "block_count,{},{}", name, count);
+        }
+        for (height, count) in &self.height_histogram {
+            let _ = writeln!(csv, "height,{},{}", height, count);
+        }
+        for chunk in &self.chunks {
+            let (x, y, z) = chunk.chunk;
+            let _ = writeln!(csv, "chunk_blocks,{} {} {},{}", x, y, z, chunk.blocks);
+        }
+        csv
+    }
+
+    fn write(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let content = if path.ends_with(".csv") {
+            self.to_csv()
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Size of the largest group of blocks connected through their faces.
+fn largest_structure(positions: impl Iterator<Item = BlockPosition>) -> usize {
+    let mut remaining: HashSet<BlockPosition> = positions.collect();
+    let mut largest = 0;
+
+    while let Some(&start) = remaining.iter().next() {
+        remaining.remove(&start);
+        let mut stack = vec![start];
+        let mut size = 0;
+
+        while let Some(BlockPosition { x, y, z }) = stack.pop() {
+            size += 1;
+
+            let neighbours = [
+                (x + 1, y, z),
+                (x - 1, y, z),
+                (x, y + 1, z),
+                (x, y - 1, z),
+                (x, y, z + 1),
+                (x, y, z - 1),
+            ];
+            for (x, y, z) in neighbours {
+                let neighbour = BlockPosition { x, y, z };
+                if remaining.remove(&neighbour) {
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        largest = largest.max(size);
+    }
+
+    largest
+}
+
+/// Path given with `--export-stats <path>`, written once the world is loaded before quitting.
+struct StatsExport(String);
+
+fn export_stats(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    input_map: Res<InputMap>,
+    registry: Res<BlockRegistry>,
+    export: Option<Res<StatsExport>>,
+    mut exit: EventWriter<AppExit>,
+    blocks: Query<(&BlockPosition, &BlockType)>,
+) {
+    let path = match &export {
+        Some(export) => export.0.as_str(),
+        None if input_map.just_pressed(Action::ExportStats, &keys, &mouse) => DEFAULT_STATS_PATH,
+        None => return,
+    };
+
+    let _span = info_span!("export_stats").entered();
+
+    let blocks: Vec<_> = blocks
+        .iter()
+        .map(|(position, block_type)| (*position, *block_type))
+        .collect();
+
+    match WorldStats::compute(&registry, &blocks).write(path) {
+        Ok(()) => info!("Exported world statistics to {}", path),
+        Err(err) => error!("Could not export world statistics to {}: {}", path, err),
+    }
+
+    if export.is_some() {
+        exit.send(AppExit);
+    }
+}
+
+/// Dumps statistics about the placed blocks to JSON or CSV, on `Action::ExportStats` or from the
+/// command line with `--export-stats <path>`.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = cli_arg("--export-stats") {
+            app.insert_resource(StatsExport(path));
+        }
+
+        app.add_system(export_stats);
+    }
+}
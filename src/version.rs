@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+/// Version and commit of this build, e.g. `0.1.0 (3e53aa8)`, to report issues against.
+pub fn version() -> String {
+    format!(
+        "{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        option_env!("GIT_COMMIT").unwrap_or("unknown commit")
+    )
+}
+
+#[cfg(feature = "update-check")]
+mod update_check {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use bevy::prelude::*;
+
+    use crate::cli_arg;
+
+    const DEFAULT_RELEASE_FEED: &str =
+        "https://api.github.com/repos/hsabouri/test_ecs/releases/latest";
+    /// Seconds the notification stays on screen.
+    const TOAST_DURATION: f32 = 10.0;
+
+    /// Tag of the latest release, filled by the request thread once it answers.
+    struct LatestRelease(Arc<Mutex<Option<String>>>);
+
+    #[derive(Component)]
+    struct UpdateToast(Timer);
+
+    /// Numbers of a version like `v1.2.3`, compared in order.
+    fn version_numbers(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map_while(|number| number.parse().ok())
+            .collect()
+    }
+
+    fn fetch_latest_release(feed: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let body = ureq::get(feed).call()?.into_string()?;
+        let release: serde_json::Value = serde_json::from_str(&body)?;
+        let tag = release["tag_name"]
+            .as_str()
+            .ok_or("release without tag_name")?;
+        Ok(tag.to_string())
+    }
+
+    /// Requests the release feed on its own thread, so a slow network never delays startup.
+    fn start_update_check(mut commands: Commands) {
+        let feed = cli_arg("--update-feed").unwrap_or_else(|| DEFAULT_RELEASE_FEED.to_string());
+        let latest = Arc::new(Mutex::new(None));
+        commands.insert_resource(LatestRelease(latest.clone()));
+
+        thread::spawn(move || match fetch_latest_release(&feed) {
+            Ok(tag) => *latest.lock().unwrap() = Some(tag),
+            Err(err) => warn!("Could not check for updates at {}: {}", feed, err),
+        });
+    }
+
+    fn show_update_toast(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        latest: Option<Res<LatestRelease>>,
+    ) {
+        let latest = match latest {
+            Some(latest) => latest,
+            None => return,
+        };
+        let tag = match latest.0.lock().unwrap().take() {
+            Some(tag) => tag,
+            None => return,
+        };
+        commands.remove_resource::<LatestRelease>();
+
+        if version_numbers(&tag) <= version_numbers(env!("CARGO_PKG_VERSION")) {
+            return;
+        }
+
+        info!("New version available: {}", tag);
+        commands
+            .spawn_bundle(
+                TextBundle::from_section(
+                    format!("New version available: {}", tag),
+                    TextStyle {
+                        font: asset_server.load("fonts/DejaVuSans.ttf"),
+                        font_size: 22.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(8.0),
+                        right: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+            )
+            .insert(UpdateToast(Timer::from_seconds(TOAST_DURATION, false)));
+    }
+
+    fn expire_update_toast(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut toasts: Query<(Entity, &mut UpdateToast)>,
+    ) {
+        for (entity, mut toast) in &mut toasts {
+            if toast.0.tick(time.delta()).finished() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    pub fn build(app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--check-updates") {
+            return;
+        }
+
+        app.add_startup_system(start_update_check)
+            .add_system(show_update_toast)
+            .add_system(expire_update_toast);
+    }
+}
+
+/// Logs the version at startup. Built with `--features update-check` and started with
+/// `--check-updates`, also looks for a newer release, from `--update-feed <url>` or the GitHub
+/// releases of the project, and shows a notification if there is one.
+pub struct VersionPlugin;
+
+fn log_version() {
+    info!("Version {}", version());
+}
+
+impl Plugin for VersionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(log_version);
+
+        #[cfg(feature = "update-check")]
+        update_check::build(app);
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderRef, ShaderType, SpecializedMeshPipelineError,
+};
+
+use crate::block::BlockRegistry;
+use crate::mesher::{ATTRIBUTE_ATLAS_RECT, ATTRIBUTE_BLOCK_TYPE, ATTRIBUTE_OCCLUSION};
+
+/// Number of block types with a color in the palette, as declared in the shader.
+pub const PALETTE_SIZE: usize = 16;
+
+/// Whether chunks are drawn with the voxel shader, enabled with `--voxel-shader`.
+pub fn enabled() -> bool {
+    std::env::args().any(|arg| arg == "--voxel-shader")
+}
+
+#[derive(ShaderType, Clone)]
+struct Palette {
+    colors: [Vec4; PALETTE_SIZE],
+}
+
+/// Material of the chunk meshes, shading each vertex from the palette color of its block type and
+/// its ambient occlusion in `assets/shaders/voxel.wgsl`, times the atlas tile of its face.
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "3f6c2a1e-8d4b-4f0a-9b7e-5c1d2e3f4a5b"]
+pub struct VoxelMaterial {
+    #[uniform(0)]
+    palette: Palette,
+    #[texture(1)]
+    #[sampler(2)]
+    atlas: Option<Handle<Image>>,
+    alpha_mode: AlphaMode,
+}
+
+impl Material for VoxelMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/voxel.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/voxel.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            ATTRIBUTE_BLOCK_TYPE.at_shader_location(2),
+            ATTRIBUTE_OCCLUSION.at_shader_location(3),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(4),
+            ATTRIBUTE_ATLAS_RECT.at_shader_location(5),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// The two variants of the voxel material, since transparent blocks need blending.
+pub struct VoxelMaterials {
+    pub opaque: Handle<VoxelMaterial>,
+    pub transparent: Handle<VoxelMaterial>,
+}
+
+impl FromWorld for VoxelMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let registry = world.resource::<BlockRegistry>();
+        let standard_materials = world.resource::<Assets<StandardMaterial>>();
+
+        // Same colors as the regular block materials, but for the textured types.
+        let mut palette = Palette {
+            colors: [Vec4::ONE; PALETTE_SIZE],
+        };
+        for block_type in registry.types().take(PALETTE_SIZE) {
+            if let Some(color) = registry.face_color(block_type, standard_materials) {
+                palette.colors[block_type.0 as usize] = Vec4::from(color.as_linear_rgba_f32());
+            }
+        }
+
+        let atlas = registry.atlas().cloned();
+        let mut materials = world.resource_mut::<Assets<VoxelMaterial>>();
+
+        VoxelMaterials {
+            opaque: materials.add(VoxelMaterial {
+                palette: palette.clone(),
+                atlas: atlas.clone(),
+                alpha_mode: AlphaMode::Opaque,
+            }),
+            transparent: materials.add(VoxelMaterial {
+                palette,
+                atlas,
+                alpha_mode: AlphaMode::Blend,
+            }),
+        }
+    }
+}
+
+/// Custom shader for the chunk meshes, to experiment with the look of blocks without recompiling.
+///
+/// Only added with `--voxel-shader`, since it replaces the lighting of the regular materials.
+pub struct VoxelMaterialPlugin;
+
+impl Plugin for VoxelMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        if enabled() {
+            app.add_plugin(MaterialPlugin::<VoxelMaterial>::default())
+                .init_resource::<VoxelMaterials>();
+        }
+    }
+}
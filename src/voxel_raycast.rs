@@ -0,0 +1,228 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_mod_raycast::{RayCastMethod, RayCastSource};
+
+use crate::block::BlockMap;
+use crate::{BlockPosition, FloorTile, MyRaycastSet};
+
+/// Rays stop after this distance, in blocks.
+const MAX_DISTANCE: f32 = 256.0;
+
+/// Where a ray enters the first occupied cell: a block, a map table or a floor tile.
+#[derive(Clone, Copy)]
+pub struct VoxelHit {
+    pub cell: BlockPosition,
+    /// Point of the face that was hit.
+    pub position: Vec3,
+    /// Unit normal of the face, pointing out of the cell.
+    pub normal: Vec3,
+}
+
+/// Face under the cursor, or in front of the fly camera, updated at the start of every frame.
+#[derive(Default)]
+pub struct PointedFace(pub Option<VoxelHit>);
+
+/// Origin and direction of the ray cast by a camera, as set by its `RayCastSource`.
+fn camera_ray(
+    method: &RayCastMethod,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Option<(Vec3, Vec3)> {
+    match method {
+        RayCastMethod::Screenspace(cursor) => {
+            let size = camera.logical_viewport_size()?;
+            let ndc = *cursor / size * 2.0 - Vec2::ONE;
+            let ndc_to_world = transform.compute_matrix() * camera.projection_matrix().inverse();
+            // The depth is reversed, the near plane being at 1.
+            let near = ndc_to_world.project_point3(ndc.extend(1.0));
+            let far = ndc_to_world.project_point3(ndc.extend(f32::EPSILON));
+            Some((near, (far - near).try_normalize()?))
+        }
+        RayCastMethod::Transform => Some((transform.translation(), transform.forward())),
+    }
+}
+
+/// Walks the cells crossed by a ray in order (Amanatides and Woo) and returns the first occupied
+/// one, within `max_distance`. The cell holding the origin is skipped, so the camera can be inside
+/// a block.
+///
+/// Cells are centered on integer coordinates. `occupied` tells the cells that stop the ray, and
+/// `flat` those only stopping it on the horizontal plane through their center, like floor tiles.
+pub fn raycast(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    occupied: impl Fn(BlockPosition) -> bool,
+    flat: impl Fn(BlockPosition) -> bool,
+) -> Option<VoxelHit> {
+    let start = (origin + Vec3::splat(0.5)).floor();
+    let mut cell = [start.x as i64, start.y as i64, start.z as i64];
+    let step = direction.signum().to_array().map(|sign| sign as i64);
+
+    // Distance along the ray to the next boundary on each axis, and between two boundaries.
+    let mut next = [0.0; 3];
+    let mut delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] != 0.0 {
+            let boundary = start[axis] + 0.5 * step[axis] as f32;
+            next[axis] = (boundary - origin[axis]) / direction[axis];
+            delta[axis] = 1.0 / direction[axis].abs();
+        } else {
+            next[axis] = f32::INFINITY;
+        }
+    }
+
+    loop {
+        let axis = if next[0] <= next[1] && next[0] <= next[2] {
+            0
+        } else if next[1] <= next[2] {
+            1
+        } else {
+            2
+        };
+
+        let entered = next[axis];
+        if entered > max_distance {
+            return None;
+        }
+        cell[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        let position = BlockPosition {
+            x: cell[0],
+            y: cell[1],
+            z: cell[2],
+        };
+        if !occupied(position) {
+            continue;
+        }
+
+        if flat(position) {
+            // Only hit if the plane is crossed before leaving the cell.
+            let left = next[0].min(next[1]).min(next[2]);
+            let crossed = (position.y as f32 - origin.y) / direction.y;
+            if direction.y != 0.0 && crossed >= entered && crossed <= left {
+                return Some(VoxelHit {
+                    cell: position,
+                    position: origin + direction * crossed,
+                    normal: Vec3::Y * -direction.y.signum(),
+                });
+            }
+            continue;
+        }
+
+        let mut normal = Vec3::ZERO;
+        normal[axis] = -step[axis] as f32;
+        return Some(VoxelHit {
+            cell: position,
+            position: origin + direction * entered,
+            normal,
+        });
+    }
+}
+
+fn update_pointed_face(
+    map: Res<BlockMap>,
+    mut pointed: ResMut<PointedFace>,
+    cameras: Query<(&Camera, &GlobalTransform, &RayCastSource<MyRaycastSet>)>,
+    floor: Query<(), With<FloorTile>>,
+) {
+    let ray = cameras
+        .iter()
+        .find_map(|(camera, transform, source)| camera_ray(&source.cast_method, camera, transform));
+
+    pointed.0 = ray.and_then(|(origin, direction)| {
+        raycast(
+            origin,
+            direction,
+            MAX_DISTANCE,
+            |cell| map.contains(cell),
+            |cell| map.get(cell).map_or(false, |entity| floor.contains(entity)),
+        )
+    });
+}
+
+/// Finds the pointed face by walking the grid along the camera ray, rather than testing the
+/// triangles of every mesh.
+pub struct VoxelRaycastPlugin;
+
+impl Plugin for VoxelRaycastPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PointedFace>()
+            .add_system_to_stage(CoreStage::PreUpdate, update_pointed_face.after(InputSystem));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: i64, y: i64, z: i64) -> BlockPosition {
+        BlockPosition { x, y, z }
+    }
+
+    fn not_flat(_: BlockPosition) -> bool {
+        false
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn axis_aligned_hit() {
+        let target = cell(5, 0, 0);
+        let hit = raycast(Vec3::ZERO, Vec3::X, 10.0, |c| c == target, not_flat).unwrap();
+
+        assert_eq!(hit.cell, target);
+        assert_eq!(hit.normal, Vec3::NEG_X);
+        assert_near(hit.position, Vec3::new(4.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn diagonal_hit_normal() {
+        let direction = Vec3::new(2.0, 1.0, 0.0).normalize();
+
+        // Entered from below, through the y = 0.5 boundary at x = 1.
+        let target = cell(1, 1, 0);
+        let hit = raycast(Vec3::ZERO, direction, 10.0, |c| c == target, not_flat).unwrap();
+        assert_eq!(hit.cell, target);
+        assert_eq!(hit.normal, Vec3::NEG_Y);
+        assert_near(hit.position, Vec3::new(1.0, 0.5, 0.0));
+
+        // Entered from the side, through the x = 2.5 boundary.
+        let target = cell(3, 1, 0);
+        let hit = raycast(Vec3::ZERO, direction, 10.0, |c| c == target, not_flat).unwrap();
+        assert_eq!(hit.cell, target);
+        assert_eq!(hit.normal, Vec3::NEG_X);
+        assert_near(hit.position, Vec3::new(2.5, 1.25, 0.0));
+    }
+
+    #[test]
+    fn miss_beyond_max_distance() {
+        let target = cell(20, 0, 0);
+
+        assert!(raycast(Vec3::ZERO, Vec3::X, 10.0, |c| c == target, not_flat).is_none());
+    }
+
+    #[test]
+    fn start_inside_block() {
+        let hit = raycast(Vec3::ZERO, Vec3::X, 10.0, |_| true, not_flat).unwrap();
+
+        assert_eq!(hit.cell, cell(1, 0, 0));
+        assert_eq!(hit.normal, Vec3::NEG_X);
+        assert_near(hit.position, Vec3::new(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn flat_cell_hit_on_its_plane() {
+        let tile = cell(0, 0, 3);
+        let origin = Vec3::new(0.0, 2.0, 0.0);
+        let direction = Vec3::new(0.0, -2.0, 3.0).normalize();
+        let hit = raycast(origin, direction, 10.0, |c| c == tile, |c| c == tile).unwrap();
+
+        assert_eq!(hit.cell, tile);
+        assert_eq!(hit.normal, Vec3::Y);
+        assert_near(hit.position, Vec3::new(0.0, 0.0, 3.0));
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+
+use bevy::prelude::*;
+
+use crate::block::{BlockRegistry, BlockType};
+use crate::chunk::Chunks;
+use crate::persistence::WorldFile;
+use crate::{cli_arg, spawn_cube, BlockPosition, WORLD_LIMIT};
+
+/// Blocks between two points of the coarsest noise lattice, the typical width of a hill.
+const FEATURE_SIZE: f32 = 16.0;
+/// Layers of dirt on top of the stone.
+const DIRT_DEPTH: i64 = 2;
+
+/// Parameters of the generated terrain, set with `--worldgen` and optionally `--seed <n>`,
+/// `--amplitude <blocks>` and `--area <blocks>`.
+pub struct WorldGenConfig {
+    /// Same seed, same terrain.
+    pub seed: u64,
+    /// Height difference between the lowest and highest columns, in blocks.
+    pub amplitude: i64,
+    /// Side of the generated square, in blocks, centered on the origin.
+    pub area: i64,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        WorldGenConfig {
+            seed: 0,
+            amplitude: 8,
+            area: 32,
+        }
+    }
+}
+
+impl WorldGenConfig {
+    fn from_args() -> Self {
+        let default = WorldGenConfig::default();
+        let arg = |flag: &str| cli_arg(flag).and_then(|value| value.parse().ok());
+
+        WorldGenConfig {
+            seed: arg("--seed").unwrap_or(default.seed),
+            amplitude: arg("--amplitude").unwrap_or(default.amplitude),
+            area: arg("--area").unwrap_or(default.area),
+        }
+    }
+
+    /// Height of the column at `x`, `z`, from 1 to `amplitude + 1`.
+    fn height(&self, x: i64, z: i64) -> i64 {
+        let (x, z) = (x as f32 / FEATURE_SIZE, z as f32 / FEATURE_SIZE);
+        // A finer octave adds bumps to the hills.
+        let noise = (value_noise(self.seed, x, z)
+            + 0.5 * value_noise(self.seed.wrapping_add(1), x * 2.0, z * 2.0))
+            / 1.5;
+
+        1 + (noise * self.amplitude as f32).round() as i64
+    }
+}
+
+/// Pseudo-random value from 0 to 1 for a lattice point (splitmix64).
+fn lattice_value(seed: u64, x: i64, z: i64) -> f32 {
+    let mut hash = seed
+        .wrapping_add((x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        .wrapping_add((z as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Smooth noise from 0 to 1, interpolating random values on the integer lattice.
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (x0, z0) = (x0 as i64, z0 as i64);
+
+    let top = lattice_value(seed, x0, z0) * (1.0 - tx) + lattice_value(seed, x0 + 1, z0) * tx;
+    let bottom =
+        lattice_value(seed, x0, z0 + 1) * (1.0 - tx) + lattice_value(seed, x0 + 1, z0 + 1) * tx;
+
+    top * (1.0 - tz) + bottom * tz
+}
+
+/// Fills new worlds with stone hills topped with dirt. Saved worlds already hold their terrain.
+fn generate_terrain(
+    mut commands: Commands,
+    registry: Res<BlockRegistry>,
+    mut chunks: ResMut<Chunks>,
+    config: Res<WorldGenConfig>,
+    world: Res<WorldFile>,
+) {
+    if fs::metadata(&world.path).is_ok() {
+        info!("Not generating terrain, {} already exists", world.path);
+        return;
+    }
+
+    let _span = info_span!("generate_terrain").entered();
+
+    let half = (config.area / 2).min(WORLD_LIMIT);
+    for x in -half..half {
+        for z in -half..half {
+            let height = config.height(x, z).min(WORLD_LIMIT);
+
+            for y in 1..=height {
+                let block_type = if y > height - DIRT_DEPTH {
+                    BlockType::DIRT
+                } else {
+                    BlockType::STONE
+                };
+
+                spawn_cube(
+                    &mut commands,
+                    &registry,
+                    &mut chunks,
+                    BlockPosition { x, y, z },
+                    block_type,
+                );
+            }
+        }
+    }
+}
+
+/// Seeded heightmap terrain generated at startup with `--worldgen`, on top of the floor.
+pub struct WorldGenPlugin;
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        if !std::env::args().any(|arg| arg == "--worldgen") {
+            return;
+        }
+
+        app.insert_resource(WorldGenConfig::from_args())
+            .add_startup_system(generate_terrain);
+    }
+}